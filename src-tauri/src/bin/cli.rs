@@ -0,0 +1,522 @@
+// 命令行工具：与桌面应用共用同一套处理流程，参数与 npm 版本的 flv2mp4 / avm 命令保持一致
+// 退出码：0 执行成功，1 执行出错，2 参数错误，3 部分文件处理失败，130 被 Ctrl+C 中止
+
+use clap::{Args, Parser, Subcommand};
+use dialoguer::{Input, MultiSelect};
+use ffmpeg_script::dir_config;
+use ffmpeg_script::error::Error;
+use ffmpeg_script::events::{FileStats, PlanAction, PlanEntry};
+use ffmpeg_script::jobs::{JobHandle, JobRegistry};
+use ffmpeg_script::options::{AvmOptions, CommandOptions, FlvToMp4Options, RemuxOptions, ValidationError};
+use ffmpeg_script::pipeline;
+use ffmpeg_script::presets;
+use ffmpeg_script::report::StdoutReporter;
+use ffmpeg_script::settings::Settings;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Instant;
+
+const EXIT_ERROR: u8 = 1;
+const EXIT_USAGE: u8 = 2;
+const EXIT_PARTIAL: u8 = 3;
+const EXIT_CANCELLED: u8 = 130;
+
+#[derive(Parser)]
+#[command(name = "ffmpeg-script", version, about = "基于ffmpeg的一键脚本命令，实现特定需求的一键操作")]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    #[command(name = "flv2mp4", visible_alias = "flvtomp4", about = "批量将flv文件转换成mp4文件")]
+    FlvToMp4(FlvToMp4Args),
+    #[command(name = "avm", visible_alias = "audioVideoMerger", about = "批量合并 _audio / _video 命名的音视频文件")]
+    Avm(AvmArgs),
+    #[command(name = "remux", about = "批量将其它格式的视频文件转换成 mp4 或 mkv")]
+    Remux(RemuxArgs),
+    #[command(name = "preset", about = "使用保存的预设执行命令，不指定名称时列出全部预设")]
+    Preset(PresetArgs),
+}
+
+#[derive(Args)]
+struct FlvToMp4Args {
+    #[arg(short, long, help = "是否输出相关调试信息")]
+    debug: bool,
+    #[arg(short = 'q', long, help = "是否通过交互问答来输入相关参数")]
+    inquirer: bool,
+    #[arg(short, long, help = "是否持续检查有需要转换的文件")]
+    watch: bool,
+    #[arg(short, long, default_value_t = 30, help = "指定重复执行转换检查的时间间隔，默认30秒")]
+    timeout: u64,
+    #[arg(short, long, help = "是否自动归档（暂支持按日期归档）")]
+    archive: bool,
+    #[arg(short, long, help = "转换完成后，是否移除flv源文件")]
+    remove: bool,
+    #[arg(long, help = "不跳过已转换过的文件，重新转换并覆盖已存在的输出文件")]
+    no_skip: bool,
+    #[arg(short, long, help = "指定命令行的工作目录，默认为当前目录")]
+    cwd: Option<String>,
+    #[arg(short, long, help = "指定转换成功后的输出目录，默认为工作目录下的 flv-to-mp4")]
+    output: Option<String>,
+    #[arg(short, long, help = "监视模式下使用定时轮询代替文件系统通知")]
+    poll: bool,
+    #[arg(short, long, value_name = "SECONDS", help = "文件最后修改后需静置的秒数，0 表示不检查")]
+    settle: Option<u64>,
+    #[arg(short = 'n', long, value_name = "COUNT", help = "文件大小需连续保持不变的检查次数，0 表示不检查")]
+    stable_checks: Option<u32>,
+    #[command(flatten)]
+    removal: RemovalArgs,
+    #[command(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Args)]
+struct AvmArgs {
+    #[arg(help = "指定命令行的工作目录，默认为当前目录")]
+    cwd: Option<String>,
+    #[arg(short = 'q', long, help = "是否通过交互问答来输入相关参数")]
+    inquirer: bool,
+    #[command(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Args)]
+struct RemuxArgs {
+    #[arg(short, long, help = "是否输出相关调试信息")]
+    debug: bool,
+    #[arg(short, long, help = "转换完成后，是否移除源文件")]
+    remove: bool,
+    #[arg(short, long, help = "指定命令行的工作目录，默认为当前目录")]
+    cwd: Option<String>,
+    #[arg(short, long, help = "指定转换成功后的输出目录")]
+    output: Option<String>,
+    #[arg(short, long = "ext", value_name = "EXT,...", help = "需要转换的源文件扩展名，可重复指定")]
+    extensions: Vec<String>,
+    #[arg(short, long, help = "目标容器：mp4 或 mkv，默认 mp4")]
+    format: Option<String>,
+    #[arg(long, help = "允许写入目标容器仅实验性支持的编码")]
+    strict: bool,
+    #[command(flatten)]
+    removal: RemovalArgs,
+    #[command(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Args)]
+struct PresetArgs {
+    #[arg(help = "预设名称")]
+    name: Option<String>,
+}
+
+// flv2mp4 与 remux 共用的源文件移除参数
+#[derive(Args)]
+struct RemovalArgs {
+    #[arg(long, value_name = "delete|trash|hold", help = "源文件的移除方式，默认移到回收站")]
+    remove_mode: Option<String>,
+    #[arg(long, value_name = "DIR", help = "暂存目录，默认为输出目录下的 removed")]
+    hold_dir: Option<String>,
+    #[arg(long, value_name = "DAYS", help = "暂存目录中的文件保留的天数，0 表示一直保留")]
+    retention: Option<u32>,
+    #[arg(long, value_name = "SECONDS", help = "输出文件与源文件的时长允许相差的秒数")]
+    duration_tolerance: Option<f64>,
+}
+
+// 三个命令共用的参数
+#[derive(Args)]
+struct CommonArgs {
+    #[arg(short, long, value_name = "N", help = "同时处理的文件数")]
+    jobs: Option<usize>,
+    #[arg(short = 'R', long, help = "递归扫描子目录")]
+    recursive: bool,
+    #[arg(long, value_name = "N", help = "递归扫描的最大深度")]
+    depth: Option<usize>,
+    #[arg(long, value_name = "GLOB", help = "只处理匹配的文件，可重复指定")]
+    include: Vec<String>,
+    #[arg(long, value_name = "GLOB", help = "排除匹配的文件，可重复指定")]
+    exclude: Vec<String>,
+    #[arg(long, value_name = "skip|files|follow", help = "符号链接的处理方式")]
+    symlinks: Option<String>,
+    #[arg(long, value_name = "N", help = "失败后最多尝试的次数，0 表示不限制")]
+    max_attempts: Option<u32>,
+    #[arg(long, value_name = "SECONDS", help = "失败后首次重试前等待的秒数")]
+    retry_delay: Option<u64>,
+    #[arg(long, help = "失败次数达到上限后将源文件移到 failed 目录")]
+    quarantine: bool,
+    #[arg(long, help = "输出路径模板，如 {date:%Y-%m}/{stem}.{ext}")]
+    template: Option<String>,
+    #[arg(long, value_name = "mtime|ctime|name", help = "模板中日期的来源")]
+    date_source: Option<String>,
+    #[arg(long, value_name = "REGEX", help = "从文件名中提取模板占位符的正则表达式")]
+    name_pattern: Option<String>,
+    #[arg(long, value_name = "skip|overwrite|suffix|compare", help = "输出文件已存在时的处理方式")]
+    collision: Option<String>,
+    #[arg(long, help = "只输出执行计划，不处理任何文件")]
+    dry_run: bool,
+}
+
+// 将参数还原成 options 模块解析的参数列表，桌面应用与命令行使用同一套解析与校验逻辑
+fn push_value(args: &mut Vec<String>, name: &str, value: Option<impl ToString>) {
+    if let Some(value) = value {
+        args.push(name.to_string());
+        args.push(value.to_string());
+    }
+}
+
+fn push_flag(args: &mut Vec<String>, name: &str, enabled: bool) {
+    if enabled {
+        args.push(name.to_string());
+    }
+}
+
+impl RemovalArgs {
+    fn push_args(&self, args: &mut Vec<String>) {
+        push_value(args, "--remove-mode", self.remove_mode.as_ref());
+        push_value(args, "--hold-dir", self.hold_dir.as_ref());
+        push_value(args, "--retention", self.retention);
+        push_value(args, "--duration-tolerance", self.duration_tolerance);
+    }
+}
+
+impl CommonArgs {
+    fn push_args(&self, args: &mut Vec<String>) {
+        push_value(args, "--jobs", self.jobs);
+        push_flag(args, "--recursive", self.recursive);
+        push_value(args, "--depth", self.depth);
+        for pattern in &self.include {
+            push_value(args, "--include", Some(pattern));
+        }
+        for pattern in &self.exclude {
+            push_value(args, "--exclude", Some(pattern));
+        }
+        push_value(args, "--symlinks", self.symlinks.as_ref());
+        push_value(args, "--max-attempts", self.max_attempts);
+        push_value(args, "--retry-delay", self.retry_delay);
+        push_flag(args, "--quarantine", self.quarantine);
+        push_value(args, "--template", self.template.as_ref());
+        push_value(args, "--date-source", self.date_source.as_ref());
+        push_value(args, "--name-pattern", self.name_pattern.as_ref());
+        push_value(args, "--collision", self.collision.as_ref());
+        push_flag(args, "--dry-run", self.dry_run);
+    }
+}
+
+impl FlvToMp4Args {
+    fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        push_value(&mut args, "--cwd", self.cwd.as_ref());
+        push_value(&mut args, "--output", self.output.as_ref());
+        push_flag(&mut args, "--watch", self.watch);
+        push_flag(&mut args, "--archive", self.archive);
+        push_flag(&mut args, "--remove", self.remove);
+        push_flag(&mut args, "--no-skip", self.no_skip);
+        push_flag(&mut args, "--debug", self.debug);
+        push_flag(&mut args, "--poll", self.poll);
+        push_value(&mut args, "--timeout", Some(self.timeout));
+        push_value(&mut args, "--settle", self.settle);
+        push_value(&mut args, "--stable-checks", self.stable_checks);
+        self.removal.push_args(&mut args);
+        self.common.push_args(&mut args);
+        args
+    }
+
+    // 与 flv2mp4 -q 相同的交互问答：工作目录、输出目录、相关选项，开启监视时再询问检查间隔
+    fn prompt(&mut self) -> Result<(), String> {
+        let input_dir = absolute(self.cwd.as_deref().unwrap_or("."));
+        let output_dir = match &self.output {
+            Some(output) => absolute(output),
+            None => input_dir.join("flv-to-mp4"),
+        };
+
+        let cwd: String = Input::new()
+            .with_prompt("指定命令行的工作目录（可以是相对目录）")
+            .default(input_dir.display().to_string())
+            .interact_text()
+            .map_err(prompt_error)?;
+        let output: String = Input::new()
+            .with_prompt("指定转换成功后的输出目录（可以是相对目录）")
+            .default(output_dir.display().to_string())
+            .interact_text()
+            .map_err(prompt_error)?;
+        self.cwd = Some(absolute(&cwd).display().to_string());
+        self.output = Some(absolute(&output).display().to_string());
+
+        let choices = [
+            ("(--watch) 是否持续检查有需要转换的文件", self.watch),
+            ("(--archive) 是否自动归档（暂支持按日期归档）", self.archive),
+            ("(--remove) 转换完成后，是否移除flv源文件", self.remove),
+            ("(--skip) 是否自动跳过已转换过的文件", !self.no_skip),
+            ("(--debug) 是否输出相关调试信息", self.debug),
+        ];
+        let selected = MultiSelect::new()
+            .with_prompt("相关选项")
+            .items(&choices.map(|(name, _)| name))
+            .defaults(&choices.map(|(_, checked)| checked))
+            .interact()
+            .map_err(prompt_error)?;
+        self.watch = selected.contains(&0);
+        self.archive = selected.contains(&1);
+        self.remove = selected.contains(&2);
+        self.no_skip = !selected.contains(&3);
+        self.debug = selected.contains(&4);
+
+        if self.watch {
+            self.timeout = Input::new()
+                .with_prompt("指定重复执行转换检查的时间间隔，默认30秒")
+                .default(self.timeout)
+                .interact_text()
+                .map_err(prompt_error)?;
+        }
+        Ok(())
+    }
+}
+
+impl AvmArgs {
+    fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(cwd) = &self.cwd {
+            args.push(cwd.clone());
+        }
+        self.common.push_args(&mut args);
+        args
+    }
+
+    fn prompt(&mut self) -> Result<(), String> {
+        let input_dir = absolute(self.cwd.as_deref().unwrap_or("."));
+        let cwd: String = Input::new()
+            .with_prompt("指定命令行的工作目录（可以是相对目录）")
+            .default(input_dir.display().to_string())
+            .interact_text()
+            .map_err(prompt_error)?;
+        self.cwd = Some(absolute(&cwd).display().to_string());
+        Ok(())
+    }
+}
+
+impl RemuxArgs {
+    fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        push_value(&mut args, "--cwd", self.cwd.as_ref());
+        push_value(&mut args, "--output", self.output.as_ref());
+        for ext in &self.extensions {
+            push_value(&mut args, "--ext", Some(ext));
+        }
+        push_value(&mut args, "--format", self.format.as_ref());
+        push_flag(&mut args, "--strict", self.strict);
+        push_flag(&mut args, "--remove", self.remove);
+        push_flag(&mut args, "--debug", self.debug);
+        self.removal.push_args(&mut args);
+        self.common.push_args(&mut args);
+        args
+    }
+}
+
+fn absolute(path: &str) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| PathBuf::from(path))
+}
+
+fn prompt_error(e: dialoguer::Error) -> String {
+    format!("无法读取输入: {}", e)
+}
+
+// 交互问答需要在终端中进行
+fn ensure_terminal() -> Result<(), String> {
+    if std::io::stdin().is_terminal() {
+        Ok(())
+    } else {
+        Err("-q 需要在终端中运行".to_string())
+    }
+}
+
+// 在当前线程中执行任务，Ctrl+C 时取消任务，正在运行的 ffmpeg 进程会被终止并清理未完成的输出文件
+fn run_job(
+    command_type: &str,
+    cwd: &str,
+    f: impl FnOnce(&JobHandle, &mut FileStats) -> Result<Vec<PlanEntry>, Error>
+) -> ExitCode {
+    let registry = JobRegistry::default();
+    let job = match registry.start(command_type, cwd) {
+        Ok(job) => job,
+        Err(e) => return fail(&e.to_string()),
+    };
+    let handle = job.clone();
+    if let Err(e) = ctrlc::set_handler(move || handle.cancel()) {
+        eprintln!("无法监听 Ctrl+C: {}", e);
+    }
+
+    let start_time = Instant::now();
+    let mut stats = FileStats::default();
+    let result = f(&job, &mut stats);
+    let outcome = result.as_ref().map(|_| ()).map_err(Error::clone);
+    registry.finish(job.id, &outcome);
+
+    if job.is_cancelled() {
+        eprintln!("{}", Error::Cancelled);
+        return ExitCode::from(EXIT_CANCELLED);
+    }
+    let plan = match result {
+        Ok(plan) => plan,
+        Err(e) => return fail(&format!("执行出错: {}", e)),
+    };
+    for entry in &plan {
+        print_plan_entry(entry);
+    }
+
+    println!(
+        "命令执行完成：成功 {} 个，跳过 {} 个，失败 {} 个，耗时 {:.2}s",
+        stats.converted, stats.skipped, stats.failed, start_time.elapsed().as_secs_f64()
+    );
+    if stats.failed > 0 {
+        ExitCode::from(EXIT_PARTIAL)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+// 输出预演模式下单个文件的处理计划
+fn print_plan_entry(entry: &PlanEntry) {
+    match entry.action {
+        PlanAction::Process => {
+            println!("[处理] {} => {}", entry.file, entry.dest.as_deref().unwrap_or_default());
+            println!("       {}", entry.argv.join(" "));
+            if entry.remove_source {
+                println!("       成功后移除源文件");
+            }
+        },
+        PlanAction::Skip => {
+            let reason = entry.reason.as_ref()
+                .and_then(|reason| serde_json::to_value(reason).ok())
+                .and_then(|value| value.as_str().map(str::to_string))
+                .unwrap_or_default();
+            println!("[跳过] {}（{}）", entry.file, reason);
+        },
+    }
+}
+
+fn fail(msg: &str) -> ExitCode {
+    eprintln!("{}", msg);
+    ExitCode::from(EXIT_ERROR)
+}
+
+// 参数校验失败时逐条输出错误
+fn invalid(errors: &[ValidationError]) -> ExitCode {
+    for error in errors {
+        eprintln!("参数错误 {}", error);
+    }
+    ExitCode::from(EXIT_USAGE)
+}
+
+fn main() -> ExitCode {
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(e) => {
+            let _ = e.print();
+            return if e.use_stderr() { ExitCode::from(EXIT_USAGE) } else { ExitCode::SUCCESS };
+        }
+    };
+
+    // 列出预设不需要 ffmpeg
+    if let Commands::Preset(PresetArgs { name: None }) = &cli.command {
+        return print_presets();
+    }
+
+    // 参数值无效与参数校验失败一样属于参数错误，在检查 ffmpeg 之前报告
+    let parsed = match cli.command {
+        Commands::FlvToMp4(mut args) => {
+            if args.inquirer {
+                if let Err(e) = ensure_terminal().and_then(|_| args.prompt()) {
+                    return fail(&e);
+                }
+            }
+            CommandOptions::parse("flv2mp4", &args.to_args())
+        },
+        Commands::Avm(mut args) => {
+            if args.inquirer {
+                if let Err(e) = ensure_terminal().and_then(|_| args.prompt()) {
+                    return fail(&e);
+                }
+            }
+            CommandOptions::parse("avm", &args.to_args())
+        },
+        Commands::Remux(args) => CommandOptions::parse("remux", &args.to_args()),
+        Commands::Preset(args) => match presets::find(args.name.as_deref().unwrap_or_default()) {
+            Ok(mut preset) => {
+                // 没有保存工作目录的预设在当前目录执行
+                let cwd = preset.params.cwd_mut();
+                if cwd.is_empty() {
+                    *cwd = absolute(".").display().to_string();
+                }
+                preset.params.into_options()
+            },
+            Err(e) => return fail(&e.to_string()),
+        },
+    };
+    let options = match parsed {
+        Ok(options) => options,
+        Err(errors) => return invalid(&errors),
+    };
+    // 工作目录中的配置文件合并后同样需要通过校验
+    match dir_config::command_options(&options) {
+        Ok(_) => {},
+        Err(Error::Validation(errors)) => return invalid(&errors),
+        Err(e) => return fail(&e.to_string()),
+    }
+
+    if let Err(e) = pipeline::check_ffmpeg_installed() {
+        return fail(&e.to_string());
+    }
+
+    match options {
+        CommandOptions::FlvToMp4(options) => run_flv_to_mp4(options),
+        CommandOptions::Avm(options) => run_avm(options),
+        CommandOptions::Remux(options) => run_remux(options),
+    }
+}
+
+fn run_flv_to_mp4(options: FlvToMp4Options) -> ExitCode {
+    if options.debug {
+        println!("输入输出目录：{} {}", options.cwd, options.output_dir);
+    }
+
+    run_job("flv2mp4", &options.cwd.clone(), |job, stats| {
+        // 监视模式一直执行到按下 Ctrl+C，预演模式只执行一次
+        if options.watch && !options.dry_run {
+            pipeline::watch_flv_to_mp4(&options, &StdoutReporter::terminal(), job, stats);
+            Ok(Vec::new())
+        } else {
+            pipeline::handle_flv_to_mp4(&options, &StdoutReporter::terminal(), Some(job), stats, None)
+        }
+    })
+}
+
+fn run_avm(options: AvmOptions) -> ExitCode {
+    run_job("avm", &options.cwd.clone(), |job, stats| {
+        pipeline::handle_audio_video_merger(&options, &StdoutReporter::terminal(), Some(job), stats)
+    })
+}
+
+fn run_remux(options: RemuxOptions) -> ExitCode {
+    run_job("remux", &options.cwd.clone(), |job, stats| {
+        pipeline::handle_remux(&options, &StdoutReporter::terminal(), Some(job), stats)
+    })
+}
+
+// 每个预设一行：名称、命令类型与工作目录
+fn print_presets() -> ExitCode {
+    let settings = match Settings::load() {
+        Ok(settings) => settings,
+        Err(e) => return fail(&e.to_string()),
+    };
+    if settings.presets.is_empty() {
+        println!("还没有保存任何预设，可以在应用界面中保存");
+    }
+    for preset in &settings.presets {
+        println!("{}\t{}\t{}", preset.name, preset.params.command_type(), preset.params.cwd());
+    }
+    if !settings.invalid_presets.is_empty() {
+        eprintln!("另有 {} 个预设无法读取，已原样保留在设置文件的 invalidPresets 中", settings.invalid_presets.len());
+    }
+    ExitCode::SUCCESS
+}
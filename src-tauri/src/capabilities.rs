@@ -0,0 +1,259 @@
+// ffmpeg 的编译配置与支持的格式、编解码器、比特流过滤器和滤镜
+// 精简构建的 ffmpeg 可能缺少 mp4 封装等功能，处理前先检查，缺少时直接说明原因，而不是让每个文件都失败
+
+use crate::error::Error;
+use crate::ffmpeg;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::sync::{Mutex, PoisonError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CodecKind {
+    Video,
+    Audio,
+    Subtitle,
+    Data,
+    Attachment,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Codec {
+    pub name: String,
+    pub kind: CodecKind,
+    pub description: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Capabilities {
+    pub ffmpeg: PathBuf,
+    // ffmpeg -version 输出的第一行
+    pub version: String,
+    // 编译选项，如 "--enable-libx264"
+    pub configuration: Vec<String>,
+    pub muxers: BTreeSet<String>,
+    // "mov,mp4,m4a" 这样的多个名称会拆开分别记录
+    pub demuxers: BTreeSet<String>,
+    pub encoders: Vec<Codec>,
+    pub decoders: Vec<Codec>,
+    pub bsfs: BTreeSet<String>,
+    pub filters: BTreeSet<String>,
+}
+
+// 处理流程依赖的 ffmpeg 功能
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Requirement {
+    Muxer(&'static str),
+    Demuxer(&'static str),
+    Encoder(&'static str),
+    Decoder(&'static str),
+    Bsf(&'static str),
+    Filter(&'static str),
+}
+
+impl Requirement {
+    fn describe(&self) -> String {
+        match self {
+            Requirement::Muxer(name) => format!("{} 封装格式（muxer）", name),
+            Requirement::Demuxer(name) => format!("{} 解封装格式（demuxer）", name),
+            Requirement::Encoder(name) => format!("{} 编码器", name),
+            Requirement::Decoder(name) => format!("{} 解码器", name),
+            Requirement::Bsf(name) => format!("{} 比特流过滤器（bsf）", name),
+            Requirement::Filter(name) => format!("{} 滤镜", name),
+        }
+    }
+}
+
+impl Capabilities {
+    pub fn has_encoder(&self, name: &str) -> bool {
+        self.encoders.iter().any(|codec| codec.name == name)
+    }
+
+    pub fn has_decoder(&self, name: &str) -> bool {
+        self.decoders.iter().any(|codec| codec.name == name)
+    }
+
+    pub fn satisfies(&self, requirement: &Requirement) -> bool {
+        match requirement {
+            Requirement::Muxer(name) => self.muxers.contains(*name),
+            Requirement::Demuxer(name) => self.demuxers.contains(*name),
+            Requirement::Encoder(name) => self.has_encoder(name),
+            Requirement::Decoder(name) => self.has_decoder(name),
+            Requirement::Bsf(name) => self.bsfs.contains(*name),
+            Requirement::Filter(name) => self.filters.contains(*name),
+        }
+    }
+}
+
+// 文件扩展名对应的解封装与封装格式名称，未列出的扩展名不做检查
+pub fn formats_for_extension(ext: &str) -> Option<(&'static str, &'static str)> {
+    let formats = match ext.to_lowercase().as_str() {
+        "flv" => ("flv", "flv"),
+        "mp4" | "m4v" => ("mp4", "mp4"),
+        "mov" => ("mov", "mov"),
+        "m4a" => ("m4a", "ipod"),
+        "mkv" => ("matroska", "matroska"),
+        "webm" => ("webm", "webm"),
+        "ts" => ("mpegts", "mpegts"),
+        "m3u8" => ("hls", "hls"),
+        "mp3" => ("mp3", "mp3"),
+        "aac" => ("aac", "adts"),
+        "ogg" => ("ogg", "ogg"),
+        "wav" => ("wav", "wav"),
+        _ => return None,
+    };
+    Some(formats)
+}
+
+// 读取这些文件所需的解封装格式，mux 为 true 时还需要写入同一格式（输出文件与源文件的扩展名相同）
+pub fn format_requirements<'a>(files: impl IntoIterator<Item = &'a PathBuf>, mux: bool) -> Vec<Requirement> {
+    let mut requirements = Vec::new();
+    for file in files {
+        let formats = file.extension().and_then(|ext| formats_for_extension(&ext.to_string_lossy()));
+        if let Some((demuxer, muxer)) = formats {
+            requirements.push(Requirement::Demuxer(demuxer));
+            if mux {
+                requirements.push(Requirement::Muxer(muxer));
+            }
+        }
+    }
+    requirements.dedup();
+    requirements
+}
+
+// 按 ffmpeg 的路径缓存，修改 ffmpeg 的位置后重新读取
+static CACHE: Mutex<Option<Capabilities>> = Mutex::new(None);
+
+pub fn get() -> Result<Capabilities, Error> {
+    let info = ffmpeg::resolve()?;
+    let mut cache = CACHE.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(capabilities) = cache.as_ref().filter(|capabilities| capabilities.ffmpeg == info.ffmpeg) {
+        return Ok(capabilities.clone());
+    }
+    let capabilities = detect(info.ffmpeg, info.version).map_err(Error::FfmpegNotFound)?;
+    *cache = Some(capabilities.clone());
+    Ok(capabilities)
+}
+
+// 检查 ffmpeg 是否具备所需的功能，缺少时返回说明
+// 无法读取功能信息时不做检查（例如 ffmpeg 未安装），由执行时的错误提示用户
+pub fn require(requirements: &[Requirement]) -> Result<(), Error> {
+    let capabilities = match get() {
+        Ok(capabilities) => capabilities,
+        Err(_) => return Ok(()),
+    };
+    let mut missing: Vec<String> = Vec::new();
+    for requirement in requirements {
+        let description = requirement.describe();
+        if !capabilities.satisfies(requirement) && !missing.contains(&description) {
+            missing.push(description);
+        }
+    }
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::FfmpegUnsupported { missing })
+    }
+}
+
+fn detect(ffmpeg: PathBuf, version: String) -> Result<Capabilities, String> {
+    let version_output = query("-version")?;
+    let configuration = version_output
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("configuration:"))
+        .map(|line| line.split_whitespace().map(str::to_string).collect())
+        .unwrap_or_default();
+
+    Ok(Capabilities {
+        ffmpeg,
+        version,
+        configuration,
+        muxers: parse_formats(&query("-muxers")?),
+        demuxers: parse_formats(&query("-demuxers")?),
+        encoders: parse_codecs(&query("-encoders")?),
+        decoders: parse_codecs(&query("-decoders")?),
+        bsfs: parse_bsfs(&query("-bsfs")?),
+        filters: parse_filters(&query("-filters")?),
+    })
+}
+
+fn query(flag: &str) -> Result<String, String> {
+    let output = ffmpeg::ffmpeg_command()
+        .args(["-hide_banner", flag])
+        .output()
+        .map_err(|e| format!("执行 ffmpeg {} 失败: {}", flag, e))?;
+    if !output.status.success() {
+        return Err(format!("执行 ffmpeg {} 失败", flag));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+// -muxers、-demuxers、-encoders、-decoders 的输出：说明之后是一行 "---"，之后每行为 " 标志 名称 描述"
+// 标志的宽度与分隔线的长度相同，其中可能含有空格，因此按列截取
+fn parse_table(output: &str) -> Vec<(String, String, String)> {
+    let mut lines = output.lines();
+    let width = lines
+        .by_ref()
+        .map(str::trim)
+        .find(|line| line.len() >= 2 && line.chars().all(|c| c == '-'))
+        .map_or(0, str::len);
+    lines
+        .filter_map(|line| {
+            let rest: String = line.chars().skip(1 + width).collect();
+            let flags: String = line.chars().skip(1).take(width).collect();
+            let mut words = rest.split_whitespace();
+            let name = words.next()?.to_string();
+            let description = words.collect::<Vec<_>>().join(" ");
+            Some((flags, name, description))
+        })
+        .collect()
+}
+
+fn parse_formats(output: &str) -> BTreeSet<String> {
+    parse_table(output)
+        .into_iter()
+        .flat_map(|(_, names, _)| names.split(',').map(str::to_string).collect::<Vec<_>>())
+        .collect()
+}
+
+fn parse_codecs(output: &str) -> Vec<Codec> {
+    parse_table(output)
+        .into_iter()
+        .filter_map(|(flags, name, description)| {
+            let kind = match flags.chars().next()? {
+                'V' => CodecKind::Video,
+                'A' => CodecKind::Audio,
+                'S' => CodecKind::Subtitle,
+                'D' => CodecKind::Data,
+                'T' => CodecKind::Attachment,
+                _ => return None,
+            };
+            Some(Codec { name, kind, description })
+        })
+        .collect()
+}
+
+// "Bitstream filters:" 之后每行一个名称
+fn parse_bsfs(output: &str) -> BTreeSet<String> {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.ends_with(':'))
+        .map(str::to_string)
+        .collect()
+}
+
+// " TSC scale  V->V  描述"，说明部分的行中没有 "->"
+fn parse_filters(output: &str) -> BTreeSet<String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            let (_, name, io) = (words.next()?, words.next()?, words.next()?);
+            io.contains("->").then(|| name.to_string())
+        })
+        .collect()
+}
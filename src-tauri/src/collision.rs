@@ -0,0 +1,206 @@
+// 输出文件已存在时的处理策略
+// 输出文件与用户自己的文件同名时，默认跳过，也可以覆盖、改名，或比较两者的内容后决定
+
+use crate::probe;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+// 比较内容时允许的时长误差（秒），复制流时容器不同会导致时长略有差异
+const DURATION_TOLERANCE: f64 = 1.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CollisionPolicy {
+    // 跳过该文件（默认）
+    Skip,
+    // 覆盖已存在的文件
+    Overwrite,
+    // 在文件名后添加 " (1)"、" (2)" 等序号
+    Suffix,
+    // 用 ffprobe 比较已存在的文件与源文件，内容相同时跳过，不同时添加序号
+    Compare,
+}
+
+impl CollisionPolicy {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "skip" => Ok(CollisionPolicy::Skip),
+            "overwrite" => Ok(CollisionPolicy::Overwrite),
+            "suffix" => Ok(CollisionPolicy::Suffix),
+            "compare" => Ok(CollisionPolicy::Compare),
+            _ => Err(format!("无效的冲突处理方式: {}（可选 skip/overwrite/suffix/compare）", value)),
+        }
+    }
+}
+
+// 检查输出路径后的结果
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Resolution {
+    // 输出文件不存在，直接写入
+    Write(PathBuf),
+    // 覆盖已存在的文件
+    Overwrite(PathBuf),
+    // 改为写入添加了序号的文件
+    Renamed(PathBuf),
+    // 输出文件已存在，跳过
+    Exists,
+    // 已存在的文件与源文件的内容相同，跳过
+    Identical,
+}
+
+// 按策略确定输出路径，reserved 为本批次中已分配给其它文件的输出路径，视同已存在
+// 本批次的其它文件稍后才会写入，除了添加序号外都按跳过处理，避免两个文件写入同一路径
+pub fn resolve(policy: CollisionPolicy, sources: &[&Path], dest: PathBuf, reserved: &HashSet<PathBuf>) -> Resolution {
+    if reserved.contains(&dest) {
+        return match policy {
+            CollisionPolicy::Suffix | CollisionPolicy::Compare => Resolution::Renamed(next_free(&dest, reserved)),
+            _ => Resolution::Exists,
+        };
+    }
+    if !dest.exists() {
+        return Resolution::Write(dest);
+    }
+    match policy {
+        CollisionPolicy::Skip => Resolution::Exists,
+        CollisionPolicy::Overwrite => Resolution::Overwrite(dest),
+        CollisionPolicy::Suffix => Resolution::Renamed(next_free(&dest, reserved)),
+        CollisionPolicy::Compare if same_content(sources, &dest) => Resolution::Identical,
+        CollisionPolicy::Compare => Resolution::Renamed(next_free(&dest, reserved)),
+    }
+}
+
+// 第一个未被占用的 "名称 (n).扩展名"
+fn next_free(dest: &Path, reserved: &HashSet<PathBuf>) -> PathBuf {
+    let stem = dest.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = dest.extension().map(|ext| ext.to_string_lossy());
+    (1..)
+        .map(|n| {
+            let name = match &ext {
+                Some(ext) => format!("{} ({}).{}", stem, n, ext),
+                None => format!("{} ({})", stem, n),
+            };
+            dest.with_file_name(name)
+        })
+        .find(|candidate| !candidate.exists() && !reserved.contains(candidate))
+        .unwrap_or_else(|| dest.to_path_buf())
+}
+
+// 已存在的文件是否就是源文件的处理结果：音视频流的编码相同，且时长一致
+// 任意一个文件无法读取时视为不同，不会因此跳过
+fn same_content(sources: &[&Path], dest: &Path) -> bool {
+    let existing = match probe::probe_media(dest) {
+        Ok(info) => info,
+        Err(_) => return false,
+    };
+    let mut expected = Vec::new();
+    let mut duration: Option<f64> = None;
+    for source in sources {
+        let info = match probe::probe_media(source) {
+            Ok(info) => info,
+            Err(_) => return false,
+        };
+        duration = match (duration, info.duration) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+        expected.extend(codecs(&info.streams));
+    }
+
+    let mut actual = codecs(&existing.streams);
+    expected.sort();
+    actual.sort();
+    let duration_matches = match (duration, existing.duration) {
+        (Some(a), Some(b)) => (a - b).abs() <= DURATION_TOLERANCE,
+        _ => false,
+    };
+    expected == actual && duration_matches
+}
+
+// 音视频流的类型与编码，其它类型的流不会复制到输出文件中
+fn codecs(streams: &[probe::StreamInfo]) -> Vec<(String, String)> {
+    streams
+        .iter()
+        .filter(|stream| stream.codec_type == "video" || stream.codec_type == "audio")
+        .map(|stream| (stream.codec_type.clone(), stream.codec_name.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn resolves_by_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        let existing = dir.path().join("a.mp4");
+        fs::write(&existing, "").unwrap();
+        fs::write(dir.path().join("a (1).mp4"), "").unwrap();
+        let missing = dir.path().join("b.mp4");
+        let reserved: HashSet<PathBuf> = [dir.path().join("c.mp4"), dir.path().join("c (1).mp4")].into();
+        let planned = dir.path().join("c.mp4");
+        let source = Path::new("/rec/a.flv");
+
+        // 已存在的 a (1).mp4 与本批次已分配的 c (1).mp4 都不会被选中
+        let cases = [
+            (CollisionPolicy::Skip, &missing, Resolution::Write(missing.clone())),
+            (CollisionPolicy::Overwrite, &missing, Resolution::Write(missing.clone())),
+            (CollisionPolicy::Skip, &existing, Resolution::Exists),
+            (CollisionPolicy::Overwrite, &existing, Resolution::Overwrite(existing.clone())),
+            (CollisionPolicy::Suffix, &existing, Resolution::Renamed(dir.path().join("a (2).mp4"))),
+            (CollisionPolicy::Skip, &planned, Resolution::Exists),
+            (CollisionPolicy::Overwrite, &planned, Resolution::Exists),
+            (CollisionPolicy::Suffix, &planned, Resolution::Renamed(dir.path().join("c (2).mp4"))),
+            (CollisionPolicy::Compare, &planned, Resolution::Renamed(dir.path().join("c (2).mp4"))),
+        ];
+        for (policy, dest, expected) in cases {
+            assert_eq!(resolve(policy, &[source], dest.clone(), &reserved), expected, "{:?} {}", policy, dest.display());
+        }
+
+        // 没有扩展名时序号加在文件名末尾
+        let no_ext = dir.path().join("d");
+        fs::write(&no_ext, "").unwrap();
+        let resolution = resolve(CollisionPolicy::Suffix, &[source], no_ext, &HashSet::new());
+        assert_eq!(resolution, Resolution::Renamed(dir.path().join("d (1)")));
+    }
+
+    #[test]
+    fn parses_policies() {
+        let cases = [
+            ("skip", Some(CollisionPolicy::Skip)),
+            ("overwrite", Some(CollisionPolicy::Overwrite)),
+            ("suffix", Some(CollisionPolicy::Suffix)),
+            ("compare", Some(CollisionPolicy::Compare)),
+            ("rename", None),
+            ("", None),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(CollisionPolicy::parse(value).ok(), expected, "{}", value);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn compare_skips_only_identical_content() {
+        use crate::probe::test_support::{fake_media as media, install_fake_ffprobe};
+
+        install_fake_ffprobe();
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("a.flv");
+        fs::write(&source, media(&[("video", "h264"), ("audio", "aac"), ("data", "amf")], "10.0")).unwrap();
+        let dest = dir.path().join("a.mp4");
+
+        // (已存在文件的内容, 期望结果)，没有 ffprobe 结果的文件视为不同
+        let renamed = Resolution::Renamed(dir.path().join("a (1).mp4"));
+        let cases = [
+            (media(&[("audio", "aac"), ("video", "h264")], "10.5"), Resolution::Identical),
+            (media(&[("video", "h264"), ("audio", "aac")], "12.0"), renamed.clone()),
+            (media(&[("video", "hevc"), ("audio", "aac")], "10.0"), renamed.clone()),
+            (media(&[("video", "h264")], "10.0"), renamed.clone()),
+            ("not json".to_string(), renamed),
+        ];
+        for (existing, expected) in cases {
+            fs::write(&dest, &existing).unwrap();
+            assert_eq!(resolve(CollisionPolicy::Compare, &[&source], dest.clone(), &HashSet::new()), expected, "{}", existing);
+        }
+    }
+}
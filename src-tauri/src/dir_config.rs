@@ -0,0 +1,189 @@
+// 工作目录中的 .ffmpeg-script.toml：每个录像目录可以带有自己的处理规则，覆盖执行命令时传入的参数
+// 在处理流程中读取，命令行工具、桌面应用与监视模式的行为相同，监视模式下每次检查都会重新读取
+// 合并后的参数会再次校验，配置文件不能绕过执行命令时的参数检查
+//
+// [flv2mp4]
+// output = "converted"             # 相对路径相对于该目录
+// archive = true
+// template = "{date:%Y}/{stem}.{ext}"
+// remove = true
+// remove-mode = "hold"
+// ffmpeg-args = ["-movflags", "+faststart"]
+//
+// [avm]
+// audio-suffix = "_a"
+// video-suffix = "_v"
+
+use crate::error::Error;
+use crate::options::{AvmOptions, CommandOptions, FlvToMp4Options, ValidationError};
+use crate::removal::{RemoveMode, RemovePolicy};
+use crate::template::{DateSource, TemplateOptions};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const FILE_NAME: &str = ".ffmpeg-script.toml";
+
+// 写错的配置项直接报错，而不是悄悄按默认参数处理
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct DirConfig {
+    #[serde(default)]
+    pub flv2mp4: FlvToMp4Config,
+    #[serde(default)]
+    pub avm: AvmConfig,
+}
+
+// 未填写的项保留传入的参数
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct FlvToMp4Config {
+    pub output: Option<String>,
+    pub archive: Option<bool>,
+    pub template: Option<String>,
+    pub date_source: Option<String>,
+    pub name_pattern: Option<String>,
+    pub remove: Option<bool>,
+    pub remove_mode: Option<String>,
+    pub hold_dir: Option<String>,
+    pub retention: Option<u32>,
+    pub duration_tolerance: Option<f64>,
+    // 追加在输出文件之前的 ffmpeg 参数
+    pub ffmpeg_args: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct AvmConfig {
+    pub output: Option<String>,
+    pub template: Option<String>,
+    pub date_source: Option<String>,
+    pub name_pattern: Option<String>,
+    // 音频与视频文件名中用于配对的后缀，默认为 _audio 与 _video
+    pub audio_suffix: Option<String>,
+    pub video_suffix: Option<String>,
+    pub ffmpeg_args: Option<Vec<String>>,
+}
+
+// 读取目录中的配置文件，不存在时返回 None
+pub fn load(dir: &Path) -> Result<Option<DirConfig>, Error> {
+    let path = dir.join(FILE_NAME);
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(Error::io("读取目录配置", &path, e)),
+    };
+    toml::from_str(&content)
+        .map(Some)
+        .map_err(|e| invalid(format!("目录配置格式错误 {}: {}", path.display(), e)))
+}
+
+// 配置文件中的错误作为参数错误返回，字段为配置文件名
+fn invalid(message: impl Into<String>) -> Error {
+    Error::Validation(vec![ValidationError::new(FILE_NAME, message)])
+}
+
+// 返回合并了工作目录中配置文件并校验后的参数，没有配置文件时与传入的参数相同
+pub fn flv_to_mp4_options(options: &FlvToMp4Options) -> Result<FlvToMp4Options, Error> {
+    let mut options = options.clone();
+    let dir = PathBuf::from(&options.cwd);
+    let Some(config) = load(&dir)? else {
+        return Ok(options);
+    };
+    let config = config.flv2mp4;
+
+    if let Some(output) = &config.output {
+        options.output_dir = resolve(&dir, output);
+    }
+    if let Some(archive) = config.archive {
+        options.archive = archive;
+    }
+    apply_template(&mut options.template, &config.template, &config.date_source, &config.name_pattern).map_err(invalid)?;
+    if let Some(remove) = config.remove {
+        options.remove = remove;
+    }
+    apply_removal(&mut options.removal, &dir, &config).map_err(invalid)?;
+    if let Some(ffmpeg_args) = config.ffmpeg_args {
+        options.ffmpeg_args = ffmpeg_args;
+    }
+    options.validate()?;
+    Ok(options)
+}
+
+pub fn avm_options(options: &AvmOptions) -> Result<AvmOptions, Error> {
+    let mut options = options.clone();
+    let dir = PathBuf::from(&options.cwd);
+    let Some(config) = load(&dir)? else {
+        return Ok(options);
+    };
+    let config = config.avm;
+
+    if let Some(output) = &config.output {
+        options.output_dir = resolve(&dir, output);
+    }
+    apply_template(&mut options.template, &config.template, &config.date_source, &config.name_pattern).map_err(invalid)?;
+    for (suffix, value) in [(&mut options.audio_suffix, config.audio_suffix), (&mut options.video_suffix, config.video_suffix)] {
+        if let Some(value) = value {
+            if value.is_empty() {
+                return Err(invalid("配对后缀不能为空"));
+            }
+            *suffix = value;
+        }
+    }
+    if options.audio_suffix == options.video_suffix {
+        return Err(invalid("音频与视频后缀不能相同"));
+    }
+    if let Some(ffmpeg_args) = config.ffmpeg_args {
+        options.ffmpeg_args = ffmpeg_args;
+    }
+    options.validate()?;
+    Ok(options)
+}
+
+// 在任务开始前合并并校验目录配置，配置有误时直接报错而不是在处理时才发现
+pub fn command_options(options: &CommandOptions) -> Result<CommandOptions, Error> {
+    Ok(match options {
+        CommandOptions::FlvToMp4(options) => CommandOptions::FlvToMp4(flv_to_mp4_options(options)?),
+        CommandOptions::Avm(options) => CommandOptions::Avm(avm_options(options)?),
+        CommandOptions::Remux(options) => CommandOptions::Remux(options.clone()),
+    })
+}
+
+fn apply_template(
+    template: &mut TemplateOptions,
+    layout: &Option<String>,
+    date_source: &Option<String>,
+    name_pattern: &Option<String>
+) -> Result<(), String> {
+    if let Some(layout) = layout {
+        template.template = Some(layout.clone());
+    }
+    if let Some(date_source) = date_source {
+        template.date_source = DateSource::parse(date_source)?;
+    }
+    if let Some(name_pattern) = name_pattern {
+        template.name_pattern = Some(name_pattern.clone());
+    }
+    Ok(())
+}
+
+fn apply_removal(removal: &mut RemovePolicy, dir: &Path, config: &FlvToMp4Config) -> Result<(), String> {
+    if let Some(mode) = &config.remove_mode {
+        removal.mode = RemoveMode::parse(mode)?;
+    }
+    if let Some(hold_dir) = &config.hold_dir {
+        removal.hold_dir = Some(resolve(dir, hold_dir));
+    }
+    if let Some(retention) = config.retention {
+        removal.retention_days = retention;
+    }
+    if let Some(tolerance) = config.duration_tolerance {
+        removal.duration_tolerance = tolerance;
+    }
+    Ok(())
+}
+
+// 配置文件中的相对路径相对于配置文件所在的目录
+fn resolve(dir: &Path, path: &str) -> String {
+    dir.join(path).to_string_lossy().to_string()
+}
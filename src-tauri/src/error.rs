@@ -0,0 +1,114 @@
+// 返回给界面的错误：code 为固定的错误代码，界面据此区分错误类型，message 为提示文字，details 为附加信息
+// {"code": "FFMPEG_FAILED", "message": "ffmpeg 执行失败（退出码 1）: ...", "details": {"exitCode": 1, "stderrTail": "..."}}
+
+use crate::options::{describe_errors, ValidationError};
+use crate::settings::CONFIG_DIR_ENV;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use serde_json::json;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+// 错误信息只保留 ffmpeg 输出的最后几行，前面通常是版本与编译配置
+const STDERR_TAIL_LINES: usize = 20;
+
+// 需要 Clone 才能作为事件发送，io::Error 因此放在 Arc 中
+#[derive(Clone, Debug, thiserror::Error)]
+pub enum Error {
+    // 找不到 ffmpeg，或找到的 ffmpeg 无法运行、版本过低
+    #[error("{0}")]
+    FfmpegNotFound(String),
+    // ffmpeg 缺少所需的格式、编码器或滤镜，missing 为缺少功能的说明
+    #[error("当前使用的 ffmpeg 不支持：{}，请更换完整构建的 ffmpeg", missing.join("、"))]
+    FfmpegUnsupported { missing: Vec<String> },
+    // exit_code 为 None 表示进程被信号终止
+    #[error("ffmpeg 执行失败（退出码 {}）: {stderr_tail}", exit_code.map_or("未知".to_string(), |code| code.to_string()))]
+    FfmpegFailed { exit_code: Option<i32>, stderr_tail: String },
+    // op 为执行的操作，如 "创建输出目录"
+    #[error("{op}失败 {}: {source}", path.display())]
+    Io { op: String, path: PathBuf, source: Arc<io::Error> },
+    // 设置、处理记录等文件的内容无法解析或无法写出，与读写失败区分，通常需要修复或删除该文件
+    #[error("文件内容无效 {}: {message}", path.display())]
+    InvalidData { path: PathBuf, message: String },
+    #[error("无法确定应用配置目录，可通过环境变量 {} 指定", CONFIG_DIR_ENV)]
+    ConfigDirNotFound,
+    #[error("路径无效: {}", .0.display())]
+    InvalidPath(PathBuf),
+    // ffprobe 无法读取的文件，reason 为 ffprobe 的输出
+    #[error("无法识别媒体文件 {}: {reason}", path.display())]
+    InvalidMedia { path: PathBuf, reason: String },
+    // kind 为查找的对象，如 "任务"、"预设"
+    #[error("{kind}不存在: {name}")]
+    NotFound { kind: &'static str, name: String },
+    #[error("{cwd} 目录下已有 {command_type} 任务正在执行")]
+    JobRunning { command_type: String, cwd: String },
+    #[error("任务已取消")]
+    Cancelled,
+    #[error("参数错误: {}", describe_errors(.0))]
+    Validation(Vec<ValidationError>),
+}
+
+impl Error {
+    pub fn io(op: &str, path: impl Into<PathBuf>, source: io::Error) -> Self {
+        Error::Io { op: op.to_string(), path: path.into(), source: Arc::new(source) }
+    }
+
+    pub fn invalid_data(path: impl Into<PathBuf>, message: impl Into<String>) -> Self {
+        Error::InvalidData { path: path.into(), message: message.into() }
+    }
+
+    pub fn ffmpeg_failed(exit_code: Option<i32>, stderr: &str) -> Self {
+        let lines: Vec<&str> = stderr.trim_end().lines().collect();
+        let stderr_tail = lines[lines.len().saturating_sub(STDERR_TAIL_LINES)..].join("\n");
+        Error::FfmpegFailed { exit_code, stderr_tail }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::FfmpegNotFound(_) => "FFMPEG_NOT_FOUND",
+            Error::FfmpegUnsupported { .. } => "FFMPEG_UNSUPPORTED",
+            Error::FfmpegFailed { .. } => "FFMPEG_FAILED",
+            Error::Io { .. } => "IO",
+            Error::InvalidData { .. } => "INVALID_DATA",
+            Error::ConfigDirNotFound => "CONFIG_DIR_NOT_FOUND",
+            Error::InvalidPath(_) => "INVALID_PATH",
+            Error::InvalidMedia { .. } => "INVALID_MEDIA",
+            Error::NotFound { .. } => "NOT_FOUND",
+            Error::JobRunning { .. } => "JOB_RUNNING",
+            Error::Cancelled => "CANCELLED",
+            Error::Validation(_) => "VALIDATION",
+        }
+    }
+
+    fn details(&self) -> serde_json::Value {
+        match self {
+            Error::FfmpegFailed { exit_code, stderr_tail } => json!({ "exitCode": exit_code, "stderrTail": stderr_tail }),
+            Error::FfmpegUnsupported { missing } => json!({ "missing": missing }),
+            Error::Io { op, path, .. } => json!({ "op": op, "path": path }),
+            Error::InvalidData { path, message } => json!({ "path": path, "message": message }),
+            Error::InvalidPath(path) => json!({ "path": path }),
+            Error::InvalidMedia { path, reason } => json!({ "path": path, "reason": reason }),
+            Error::NotFound { kind, name } => json!({ "kind": kind, "name": name }),
+            Error::JobRunning { command_type, cwd } => json!({ "commandType": command_type, "cwd": cwd }),
+            Error::Validation(errors) => json!({ "errors": errors }),
+            _ => serde_json::Value::Null,
+        }
+    }
+}
+
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Error", 3)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("details", &self.details())?;
+        state.end()
+    }
+}
+
+impl From<Vec<ValidationError>> for Error {
+    fn from(errors: Vec<ValidationError>) -> Self {
+        Error::Validation(errors)
+    }
+}
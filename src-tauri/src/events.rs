@@ -0,0 +1,134 @@
+// 发送给前端的结构化事件
+
+use crate::error::Error;
+use crate::progress::FfmpegProgress;
+use serde::Serialize;
+
+pub const COMMAND_COMPLETE: &str = "command-complete";
+
+// 单次任务中各类文件的处理数量
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileStats {
+    pub converted: u32,
+    pub skipped: u32,
+    pub failed: u32,
+}
+
+// 任务结束时发送，无论成功、失败还是被取消
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandComplete {
+    pub job_id: u64,
+    pub command_type: String,
+    pub success: bool,
+    pub cancelled: bool,
+    // 结构见 error.rs，被取消时为 CANCELLED
+    pub error: Option<Error>,
+    pub stats: FileStats,
+    // 总耗时，单位秒
+    pub elapsed: f64,
+}
+
+pub const COMMAND_EVENT: &str = "command-event";
+
+// 文件被跳过的原因
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SkipReason {
+    // 输出目录中已存在处理结果
+    OutputExists,
+    // 文件最近仍在修改，可能还在录制中
+    StillRecording,
+    // 未找到与音频文件对应的视频文件
+    NoMatchingVideo,
+    // 编码无法直接放入目标容器
+    IncompatibleCodec,
+    // m3u8 播放列表引用的分片，随播放列表一起转换
+    PlaylistSegment,
+    // ffprobe 未在文件中找到所需的音频或视频流
+    MissingStream,
+    // ffprobe 无法读取文件，只在预演模式中出现，实际执行时按处理失败记录
+    UnreadableMedia,
+    // 处理记录中显示该文件已处理过，且之后没有变化
+    AlreadyProcessed,
+    // 上次处理失败后的重试等待时间未到
+    RetryLater,
+    // 失败次数已达到上限，不再重试
+    TooManyFailures,
+    // 无法根据输出路径模板生成输出路径
+    InvalidDestination,
+    // 已存在的输出文件与源文件的内容相同
+    IdenticalOutput,
+}
+
+// 输出文件已存在时实际采取的处理方式，跳过时发送 Skipped 事件
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CollisionAction {
+    Overwrite,
+    // 改为写入添加了序号的文件
+    Rename,
+}
+
+// 预演模式下对单个文件的处理计划
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PlanAction {
+    // 执行 argv 中的 ffmpeg 命令
+    Process,
+    Skip,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlanEntry {
+    pub file: String,
+    pub action: PlanAction,
+    // 跳过的原因，只在 action 为 skip 时有值
+    pub reason: Option<SkipReason>,
+    pub dest: Option<String>,
+    // 将要执行的完整命令行，第一项为程序名
+    pub argv: Vec<String>,
+    // 处理成功后是否移除源文件
+    pub remove_source: bool,
+}
+
+// 处理过程中的文件级事件，前端可据此展示每个文件的状态与统计
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum JobEvent {
+    FileDiscovered { file: String },
+    Skipped { file: String, reason: SkipReason },
+    Started { file: String },
+    // 输出文件已存在，dest 为实际写入的路径
+    Collision { file: String, dest: String, action: CollisionAction },
+    // 当前批次的处理进度，开始处理每个文件时发送
+    Progress { file: String, current: usize, total: usize },
+    // ffmpeg 处理单个文件时的实时进度
+    FileProgress {
+        file: String,
+        #[serde(flatten)]
+        progress: FfmpegProgress,
+    },
+    // elapsed 单位为秒
+    Succeeded { file: String, elapsed: f64, dest: String },
+    Failed { file: String, stderr: String },
+    // 处理成功但输出文件未通过校验，源文件已保留
+    VerifyFailed { file: String, reason: String },
+    WatchTick { count: u32, input_dir: String, output_dir: String },
+    // 预演模式下每个文件的处理计划，不会实际处理文件
+    Planned {
+        #[serde(flatten)]
+        entry: PlanEntry,
+    },
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobEventPayload {
+    pub job_id: Option<u64>,
+    pub command_type: String,
+    #[serde(flatten)]
+    pub event: JobEvent,
+}
@@ -0,0 +1,217 @@
+// 定位 ffmpeg 与 ffprobe
+// 从桌面启动的应用通常拿不到终端中配置的 PATH，只在 PATH 中查找会误报 FFmpeg 未安装
+// 查找顺序：设置中指定的路径、应用程序所在的目录、PATH、常见的安装位置
+
+use crate::error::Error;
+use crate::settings::Settings;
+use serde::Serialize;
+use std::env::consts::EXE_SUFFIX;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Mutex, PoisonError};
+
+// 依赖 -progress 输出中的 out_time_us 等字段，更早的版本不支持
+pub const MIN_VERSION: (u32, u32) = (4, 0);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BinarySource {
+    // 设置中指定的路径
+    Setting,
+    // 与应用程序位于同一目录，可以把 ffmpeg 放在应用程序旁边使用
+    AppDir,
+    Path,
+    // Homebrew、winget 等包管理器的常见安装位置
+    CommonLocation,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FfmpegInfo {
+    pub ffmpeg: PathBuf,
+    // 找不到 ffprobe 时为 None，依赖 ffprobe 的检查会被跳过
+    pub ffprobe: Option<PathBuf>,
+    // ffmpeg -version 输出的第一行
+    pub version: String,
+    pub source: BinarySource,
+}
+
+// 第一次使用时查找，找不到的结果同样缓存，避免每个文件都重新查找一遍
+// 指定路径或调用 reset 后重新查找
+static RESOLVED: Mutex<Option<Result<FfmpegInfo, String>>> = Mutex::new(None);
+
+pub fn resolve() -> Result<FfmpegInfo, Error> {
+    if let Some(resolved) = RESOLVED.lock().unwrap_or_else(PoisonError::into_inner).clone() {
+        return resolved.map_err(Error::FfmpegNotFound);
+    }
+    // 查找时会逐个执行候选程序，期间不持有锁，同时查找的线程以先完成的结果为准
+    let located = locate(Settings::load()?.ffmpeg_path.as_deref());
+    let mut resolved = RESOLVED.lock().unwrap_or_else(PoisonError::into_inner);
+    resolved.get_or_insert(located).clone().map_err(Error::FfmpegNotFound)
+}
+
+// 清除缓存的查找结果，例如安装 ffmpeg 后再次检查时
+pub fn reset() {
+    *RESOLVED.lock().unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+// 调用 ffmpeg 的命令，找不到时仍使用 "ffmpeg"，由执行时的错误提示用户
+pub fn ffmpeg_command() -> Command {
+    match resolve() {
+        Ok(info) => Command::new(info.ffmpeg),
+        Err(_) => Command::new("ffmpeg"),
+    }
+}
+
+pub fn ffprobe_command() -> Command {
+    match resolve().ok().and_then(|info| info.ffprobe) {
+        Some(ffprobe) => Command::new(ffprobe),
+        None => Command::new("ffprobe"),
+    }
+}
+
+// 校验并保存用户指定的 ffmpeg 路径，path 为 None 时清除设置，恢复自动查找
+pub fn set_path(path: Option<&str>) -> Result<FfmpegInfo, Error> {
+    let path = path.map(str::trim).filter(|path| !path.is_empty());
+    let info = locate(path).map_err(Error::FfmpegNotFound)?;
+    let mut settings = Settings::load()?;
+    settings.ffmpeg_path = path.map(str::to_string);
+    settings.save()?;
+    *RESOLVED.lock().unwrap_or_else(PoisonError::into_inner) = Some(Ok(info.clone()));
+    Ok(info)
+}
+
+fn locate(setting: Option<&str>) -> Result<FfmpegInfo, String> {
+    if let Some(setting) = setting {
+        // 用户指定的路径不可用时直接报错，不悄悄换成其它位置的 ffmpeg
+        let ffmpeg = explicit_binary(Path::new(setting), "ffmpeg")
+            .ok_or_else(|| format!("指定的 ffmpeg 不存在: {}", setting))?;
+        let version = check_version(&ffmpeg)?;
+        let ffprobe = sibling(&ffmpeg, "ffprobe").or_else(|| search("ffprobe").map(|(path, _)| path));
+        return Ok(FfmpegInfo { ffmpeg, ffprobe, version, source: BinarySource::Setting });
+    }
+
+    let mut last_error = None;
+    for (ffmpeg, source) in candidates("ffmpeg") {
+        match check_version(&ffmpeg) {
+            Ok(version) => {
+                let ffprobe = sibling(&ffmpeg, "ffprobe").or_else(|| search("ffprobe").map(|(path, _)| path));
+                return Ok(FfmpegInfo { ffmpeg, ffprobe, version, source });
+            },
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| "FFmpeg 未安装".to_string()))
+}
+
+// 指定的路径可以是可执行文件，也可以是其所在的目录
+fn explicit_binary(path: &Path, name: &str) -> Option<PathBuf> {
+    if path.is_dir() {
+        executable(path, name)
+    } else {
+        path.is_file().then(|| path.to_path_buf())
+    }
+}
+
+// 与 ffmpeg 位于同一目录的同类程序，如 ffprobe
+fn sibling(ffmpeg: &Path, name: &str) -> Option<PathBuf> {
+    executable(ffmpeg.parent()?, name)
+}
+
+fn executable(dir: &Path, name: &str) -> Option<PathBuf> {
+    let path = dir.join(format!("{}{}", name, EXE_SUFFIX));
+    path.is_file().then_some(path)
+}
+
+fn search(name: &str) -> Option<(PathBuf, BinarySource)> {
+    candidates(name).into_iter().next()
+}
+
+// 按查找顺序列出所有存在的候选程序，同一文件只保留第一次出现的位置
+fn candidates(name: &str) -> Vec<(PathBuf, BinarySource)> {
+    let mut found: Vec<(PathBuf, BinarySource)> = Vec::new();
+    let mut push = |path: Option<PathBuf>, source: BinarySource| {
+        if let Some(path) = path {
+            if !found.iter().any(|(existing, _)| *existing == path) {
+                found.push((path, source));
+            }
+        }
+    };
+
+    if let Some(exe_dir) = std::env::current_exe().ok().and_then(|exe| exe.parent().map(Path::to_path_buf)) {
+        push(executable(&exe_dir, name), BinarySource::AppDir);
+    }
+    if let Some(path) = std::env::var_os("PATH") {
+        for dir in std::env::split_paths(&path) {
+            push(executable(&dir, name), BinarySource::Path);
+        }
+    }
+    for dir in common_locations() {
+        push(executable(&dir, name), BinarySource::CommonLocation);
+    }
+    found
+}
+
+#[cfg(not(windows))]
+fn common_locations() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = [
+        "/opt/homebrew/bin",
+        "/usr/local/bin",
+        "/usr/bin",
+        "/opt/local/bin",
+        "/home/linuxbrew/.linuxbrew/bin",
+        "/snap/bin",
+    ]
+    .iter()
+    .map(PathBuf::from)
+    .collect();
+    if let Some(home) = dirs::home_dir() {
+        dirs.push(home.join(".local/bin"));
+        dirs.push(home.join("bin"));
+    }
+    dirs
+}
+
+#[cfg(windows)]
+fn common_locations() -> Vec<PathBuf> {
+    let mut dirs = vec![PathBuf::from(r"C:\ffmpeg\bin")];
+    let env_dir = |name: &str, relative: &str| std::env::var_os(name).map(|dir| PathBuf::from(dir).join(relative));
+    dirs.extend(env_dir("ProgramFiles", r"ffmpeg\bin"));
+    dirs.extend(env_dir("LOCALAPPDATA", r"Microsoft\WinGet\Links"));
+    dirs.extend(env_dir("USERPROFILE", r"scoop\shims"));
+    dirs.extend(env_dir("ProgramData", r"chocolatey\bin"));
+    dirs
+}
+
+// 执行 -version，返回版本信息的第一行，低于最低版本时返回错误
+fn check_version(ffmpeg: &Path) -> Result<String, String> {
+    let output = Command::new(ffmpeg)
+        .arg("-version")
+        .output()
+        .map_err(|e| format!("无法运行 {}: {}", ffmpeg.display(), e))?;
+    if !output.status.success() {
+        return Err(format!("FFmpeg 无法运行: {}", ffmpeg.display()));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let line = stdout.lines().next().unwrap_or("").to_string();
+    if let Some(version) = parse_version(&line) {
+        if version < MIN_VERSION {
+            return Err(format!(
+                "FFmpeg 版本过低: {}.{}（{}），需要 {}.{} 或更高版本",
+                version.0, version.1, ffmpeg.display(), MIN_VERSION.0, MIN_VERSION.1
+            ));
+        }
+    }
+    Ok(line)
+}
+
+// 解析 "ffmpeg version 6.1.1-3ubuntu5"、"ffmpeg version n7.0" 中的主次版本号
+// 从源码构建的 "N-113000-g..." 与按日期命名的 "2024-03-04-git-..." 无法判断版本，返回 None
+fn parse_version(line: &str) -> Option<(u32, u32)> {
+    let version = line.split_whitespace().skip_while(|word| *word != "version").nth(1)?;
+    let version = version.strip_prefix('n').unwrap_or(version);
+    let mut parts = version.split(|c: char| !c.is_ascii_digit());
+    let major: u32 = parts.next()?.parse().ok()?;
+    let minor: u32 = parts.next().and_then(|minor| minor.parse().ok()).unwrap_or(0);
+    (major < 1000).then_some((major, minor))
+}
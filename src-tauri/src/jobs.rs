@@ -0,0 +1,285 @@
+// 后台任务注册表：记录 run_ffmpeg_command_realtime 启动的任务，支持查询状态与取消
+
+use crate::error::Error;
+use crate::options;
+use crate::progress::{self, FfmpegProgress, ProgressMessage, ProgressParser, ProgressTracker};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+use std::process::{Child, ExitStatus};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// 检查取消标记的间隔，决定了取消操作的响应速度
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// 保留的已结束任务数量，更早结束的任务在有新任务结束时清除
+const MAX_FINISHED_JOBS: usize = 50;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum JobState {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobInfo {
+    pub id: u64,
+    pub command_type: String,
+    // 规范化后的工作目录，同一目录的不同写法对应同一个值
+    pub cwd: String,
+    pub state: JobState,
+    pub started_at: u64,
+    pub finished_at: Option<u64>,
+    pub error: Option<Error>,
+}
+
+// 执行线程持有的任务句柄，cancel_job 通过共享的标记通知其停止
+#[derive(Clone, Debug)]
+pub struct JobHandle {
+    pub id: u64,
+    pub command_type: String,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl JobHandle {
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    // 可被取消打断的等待，返回 false 表示等待期间任务已被取消
+    pub fn sleep(&self, duration: Duration) -> bool {
+        let deadline = Instant::now() + duration;
+        while Instant::now() < deadline {
+            if self.is_cancelled() {
+                return false;
+            }
+            thread::sleep(POLL_INTERVAL.min(deadline - Instant::now()));
+        }
+        !self.is_cancelled()
+    }
+}
+
+// 在处理每个文件之前调用，任务已取消时直接返回错误以中止整个流程
+pub fn check_cancelled(job: Option<&JobHandle>) -> Result<(), Error> {
+    if job.is_some_and(|job| job.is_cancelled()) {
+        Err(Error::Cancelled)
+    } else {
+        Ok(())
+    }
+}
+
+pub struct ChildOutput {
+    pub status: ExitStatus,
+    pub stderr: String,
+}
+
+// 等待子进程结束，期间若任务被取消则杀掉子进程
+// 子进程的 stdout 需为 ffmpeg -progress pipe:1 的输出，解析到的进度通过 on_progress 回调
+// 返回 None 表示等待期间任务被取消
+pub fn wait_child(
+    mut child: Child,
+    job: Option<&JobHandle>,
+    on_progress: &mut dyn FnMut(FfmpegProgress),
+) -> io::Result<Option<ChildOutput>> {
+    let (tx, rx) = mpsc::channel();
+
+    // 在单独的线程中读取 stdout 与 stderr，避免管道写满导致 ffmpeg 阻塞
+    let stdout_reader = child.stdout.take().map(|stdout| {
+        let tx = tx.clone();
+        thread::spawn(move || {
+            let mut parser = ProgressParser::default();
+            for_each_line(stdout, |line| {
+                if let Some(message) = parser.feed(line) {
+                    let _ = tx.send(message);
+                }
+            });
+        })
+    });
+    let stderr_reader = child.stderr.take().map(|stderr| {
+        let tx = tx.clone();
+        thread::spawn(move || {
+            let mut buf = String::new();
+            for_each_line(stderr, |line| {
+                if let Some(duration) = progress::parse_duration(line) {
+                    let _ = tx.send(ProgressMessage::Duration(duration));
+                }
+                buf.push_str(line);
+            });
+            buf
+        })
+    });
+    drop(tx);
+
+    let mut tracker = ProgressTracker::default();
+    let mut drain = |tracker: &mut ProgressTracker| {
+        for message in rx.try_iter() {
+            if let Some(progress) = tracker.update(message) {
+                on_progress(progress);
+            }
+        }
+    };
+
+    let status = loop {
+        drain(&mut tracker);
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if job.is_some_and(|job| job.is_cancelled()) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(None);
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    if let Some(reader) = stdout_reader {
+        let _ = reader.join();
+    }
+    let stderr = stderr_reader
+        .and_then(|reader| reader.join().ok())
+        .unwrap_or_default();
+    drain(&mut tracker);
+
+    Ok(Some(ChildOutput { status, stderr }))
+}
+
+// 按行读取直到管道关闭，非 UTF-8 内容按有损方式转换，保证管道始终被读空
+fn for_each_line(reader: impl Read, mut f: impl FnMut(&str)) {
+    let mut reader = BufReader::new(reader);
+    let mut line = Vec::new();
+    while reader.read_until(b'\n', &mut line).is_ok_and(|n| n > 0) {
+        f(&String::from_utf8_lossy(&line));
+        line.clear();
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+struct JobEntry {
+    info: JobInfo,
+    handle: JobHandle,
+}
+
+// 作为 Tauri managed state 注册，clone 后可在执行线程中使用
+#[derive(Clone, Default)]
+pub struct JobRegistry {
+    next_id: Arc<AtomicU64>,
+    jobs: Arc<Mutex<HashMap<u64, JobEntry>>>,
+}
+
+impl JobRegistry {
+    // 登记一个新任务，同一目录下同类型的任务同时只允许运行一个
+    // 目录先规范化再比较，末尾的分隔符、相对路径与符号链接不会绕过检查
+    pub fn start(&self, command_type: &str, cwd: &str) -> Result<JobHandle, Error> {
+        let cwd = options::normalize(Path::new(cwd)).display().to_string();
+        let cwd = cwd.as_str();
+        let mut jobs = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let duplicated = jobs.values().any(|entry| {
+            entry.info.state == JobState::Running
+                && entry.info.command_type == command_type
+                && entry.info.cwd == cwd
+        });
+        if duplicated {
+            return Err(Error::JobRunning { command_type: command_type.to_string(), cwd: cwd.to_string() });
+        }
+
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+        let handle = JobHandle {
+            id,
+            command_type: command_type.to_string(),
+            cancelled: Arc::new(AtomicBool::new(false)),
+        };
+        let info = JobInfo {
+            id,
+            command_type: command_type.to_string(),
+            cwd: cwd.to_string(),
+            state: JobState::Running,
+            started_at: now_millis(),
+            finished_at: None,
+            error: None,
+        };
+        jobs.insert(id, JobEntry { info, handle: handle.clone() });
+
+        Ok(handle)
+    }
+
+    // 执行线程结束时调用，根据执行结果与取消标记记录最终状态
+    pub fn finish(&self, id: u64, result: &Result<(), Error>) {
+        let mut jobs = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(entry) = jobs.get_mut(&id) {
+            entry.info.state = match result {
+                _ if entry.handle.is_cancelled() => JobState::Cancelled,
+                Ok(_) => JobState::Completed,
+                Err(_) => JobState::Failed,
+            };
+            entry.info.error = result.as_ref().err().cloned();
+            entry.info.finished_at = Some(now_millis());
+        }
+        prune(&mut jobs);
+    }
+
+    pub fn cancel(&self, id: u64) -> Result<(), Error> {
+        let jobs = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+        let entry = jobs.get(&id).ok_or_else(|| not_found(id))?;
+        if entry.info.state == JobState::Running {
+            entry.handle.cancel();
+        }
+        Ok(())
+    }
+
+    pub fn list(&self) -> Vec<JobInfo> {
+        let mut list: Vec<JobInfo> = self
+            .jobs
+            .lock()
+            .map(|jobs| jobs.values().map(|entry| entry.info.clone()).collect())
+            .unwrap_or_default();
+        list.sort_by_key(|info| info.id);
+        list
+    }
+
+    pub fn status(&self, id: u64) -> Result<JobInfo, Error> {
+        let jobs = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+        jobs.get(&id)
+            .map(|entry| entry.info.clone())
+            .ok_or_else(|| not_found(id))
+    }
+}
+
+// 只保留最近结束的 MAX_FINISHED_JOBS 个任务，避免应用长时间运行时任务列表不断增长
+fn prune(jobs: &mut HashMap<u64, JobEntry>) {
+    let mut finished: Vec<(Option<u64>, u64)> = jobs
+        .values()
+        .filter(|entry| entry.info.state != JobState::Running)
+        .map(|entry| (entry.info.finished_at, entry.info.id))
+        .collect();
+    if finished.len() <= MAX_FINISHED_JOBS {
+        return;
+    }
+    finished.sort_unstable();
+    for (_, id) in &finished[..finished.len() - MAX_FINISHED_JOBS] {
+        jobs.remove(id);
+    }
+}
+
+fn not_found(id: u64) -> Error {
+    Error::NotFound { kind: "任务", name: id.to_string() }
+}
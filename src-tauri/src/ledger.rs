@@ -0,0 +1,330 @@
+// 已处理文件的记录，保存在输出目录中，按源文件路径、大小与修改时间判断文件是否处理过
+// 归档或指定了子目录时输出文件名与源文件不再一一对应，仅凭输出目录中的文件无法可靠地判断
+// 同时记录处理失败的次数，用于失败后的退避重试，避免监视模式下反复处理损坏的文件
+
+use crate::error::Error;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const LEDGER_FILE: &str = ".ffmpeg-script-ledger.json";
+
+const LEDGER_VERSION: u32 = 1;
+
+// 退避时间的上限
+const MAX_BACKOFF: Duration = Duration::from_secs(3600);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LedgerEntry {
+    pub source: String,
+    pub size: u64,
+    // 源文件的修改时间（Unix 毫秒）
+    pub modified: u64,
+    pub dest: String,
+    pub command_type: String,
+    pub processed_at: String,
+}
+
+// 处理失败的文件，文件内容变化后重新计数
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FailureEntry {
+    pub source: String,
+    pub size: u64,
+    pub modified: u64,
+    pub attempts: u32,
+    // 最近一次失败的时间（Unix 毫秒）
+    pub last_failed_at: u64,
+    // 最近一次失败时 ffmpeg 输出的最后一行
+    pub last_error: String,
+    // 移到隔离目录后的位置
+    pub quarantined: Option<String>,
+}
+
+// 失败重试策略
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    // 最多尝试的次数，0 表示不限制
+    pub max_attempts: u32,
+    // 第一次失败后等待的秒数，之后每次失败翻倍
+    pub retry_delay: u64,
+    // 达到最大尝试次数后把源文件移到输出目录下的 failed 目录
+    pub quarantine: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            retry_delay: 60,
+            quarantine: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn exhausted(&self, attempts: u32) -> bool {
+        self.max_attempts > 0 && attempts >= self.max_attempts
+    }
+
+    // 失败 attempts 次后需要等待的时间
+    fn backoff(&self, attempts: u32) -> Duration {
+        let factor = 2u64.saturating_pow(attempts.saturating_sub(1));
+        Duration::from_secs(self.retry_delay.saturating_mul(factor)).min(MAX_BACKOFF)
+    }
+}
+
+// 文件当前是否可以处理
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RetryState {
+    Ready,
+    // 上次失败后的等待时间未到，值为剩余时间
+    Waiting(Duration),
+    // 已达到最大尝试次数，值为已失败的次数
+    Exhausted(u32),
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct LedgerFile {
+    version: u32,
+    entries: Vec<LedgerEntry>,
+    #[serde(default)]
+    failures: Vec<FailureEntry>,
+}
+
+#[derive(Default)]
+struct LedgerState {
+    entries: HashMap<String, LedgerEntry>,
+    failures: HashMap<String, FailureEntry>,
+}
+
+pub struct Ledger {
+    path: PathBuf,
+    state: Mutex<LedgerState>,
+}
+
+// 记录使用的路径形式，尽量使用绝对路径，使不同的工作目录写法对应同一条记录
+fn source_key(path: &Path) -> String {
+    fs::canonicalize(path)
+        .unwrap_or_else(|_| path.to_path_buf())
+        .display()
+        .to_string()
+}
+
+// 文件当前的大小与修改时间
+fn fingerprint(path: &Path) -> io::Result<(u64, u64)> {
+    let metadata = fs::metadata(path)?;
+    let modified = metadata.modified()?.duration_since(UNIX_EPOCH).unwrap_or_default();
+    Ok((metadata.len(), modified.as_millis() as u64))
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+fn read_state(path: &Path) -> Result<LedgerState, Error> {
+    if !path.exists() {
+        return Ok(LedgerState::default());
+    }
+    let content = fs::read_to_string(path).map_err(|e| Error::io("读取处理记录", path, e))?;
+    let file: LedgerFile = serde_json::from_str(&content)
+        .map_err(|e| Error::invalid_data(path, format!("处理记录已损坏，可删除该文件后重试: {}", e)))?;
+    Ok(LedgerState {
+        entries: file
+            .entries
+            .into_iter()
+            .map(|entry| (entry.source.clone(), entry))
+            .collect(),
+        failures: file
+            .failures
+            .into_iter()
+            .map(|failure| (failure.source.clone(), failure))
+            .collect(),
+    })
+}
+
+impl Ledger {
+    // 打开输出目录中的处理记录，文件不存在时为空
+    pub fn open(output_dir: &Path) -> Result<Self, Error> {
+        let path = output_dir.join(LEDGER_FILE);
+        let state = read_state(&path)?;
+        Ok(Ledger {
+            path,
+            state: Mutex::new(state),
+        })
+    }
+
+    // 源文件是否已处理过，且处理后大小与修改时间都没有变化
+    pub fn contains(&self, source: &Path) -> bool {
+        let fingerprint = match fingerprint(source) {
+            Ok(fingerprint) => fingerprint,
+            Err(_) => return false,
+        };
+        let state = match self.state.lock() {
+            Ok(state) => state,
+            Err(_) => return false,
+        };
+        state
+            .entries
+            .get(&source_key(source))
+            .is_some_and(|entry| (entry.size, entry.modified) == fingerprint)
+    }
+
+    // 根据失败记录判断源文件现在是否可以处理，文件内容变化后视为新文件
+    pub fn retry_state(&self, source: &Path, policy: &RetryPolicy) -> RetryState {
+        let fingerprint = fingerprint(source).ok();
+        let state = match self.state.lock() {
+            Ok(state) => state,
+            Err(_) => return RetryState::Ready,
+        };
+        let failure = match state.failures.get(&source_key(source)) {
+            Some(failure) if Some((failure.size, failure.modified)) == fingerprint => failure,
+            _ => return RetryState::Ready,
+        };
+        if policy.exhausted(failure.attempts) {
+            return RetryState::Exhausted(failure.attempts);
+        }
+        let elapsed = Duration::from_millis(now_millis().saturating_sub(failure.last_failed_at));
+        let backoff = policy.backoff(failure.attempts);
+        if elapsed < backoff {
+            RetryState::Waiting(backoff - elapsed)
+        } else {
+            RetryState::Ready
+        }
+    }
+
+    // 记录处理成功的源文件，需在删除源文件之前调用
+    pub fn record(&self, source: &Path, dest: &Path, command_type: &str) -> Result<(), Error> {
+        let (size, modified) = fingerprint(source).map_err(|e| Error::io("读取文件信息", source, e))?;
+        let entry = LedgerEntry {
+            source: source_key(source),
+            size,
+            modified,
+            dest: dest.display().to_string(),
+            command_type: command_type.to_string(),
+            processed_at: chrono::Local::now().to_rfc3339(),
+        };
+
+        self.update(|state| {
+            state.failures.remove(&entry.source);
+            state.entries.insert(entry.source.clone(), entry);
+        })
+    }
+
+    // 记录一次处理失败，返回该文件累计失败的次数
+    pub fn record_failure(&self, source: &Path, error: &str) -> Result<u32, Error> {
+        let (size, modified) = fingerprint(source).map_err(|e| Error::io("读取文件信息", source, e))?;
+        let key = source_key(source);
+        let last_error = error.trim().lines().last().unwrap_or_default().to_string();
+
+        let mut attempts = 1;
+        self.update(|state| {
+            // 文件内容变化后重新计数
+            if let Some(previous) = state.failures.get(&key) {
+                if (previous.size, previous.modified) == (size, modified) {
+                    attempts = previous.attempts + 1;
+                }
+            }
+            state.failures.insert(key.clone(), FailureEntry {
+                source: key.clone(),
+                size,
+                modified,
+                attempts,
+                last_failed_at: now_millis(),
+                last_error,
+                quarantined: None,
+            });
+        })?;
+        Ok(attempts)
+    }
+
+    // 记录源文件已被移到隔离目录
+    pub fn mark_quarantined(&self, source: &Path, moved_to: &Path) -> Result<(), Error> {
+        let key = source_key(source);
+        self.update(|state| {
+            if let Some(failure) = state.failures.get_mut(&key) {
+                failure.quarantined = Some(moved_to.display().to_string());
+            }
+        })
+    }
+
+    pub fn list(&self) -> Vec<LedgerEntry> {
+        let mut list: Vec<LedgerEntry> = self
+            .state
+            .lock()
+            .map(|state| state.entries.values().cloned().collect())
+            .unwrap_or_default();
+        list.sort_by(|a, b| a.source.cmp(&b.source));
+        list
+    }
+
+    pub fn failures(&self) -> Vec<FailureEntry> {
+        let mut list: Vec<FailureEntry> = self
+            .state
+            .lock()
+            .map(|state| state.failures.values().cloned().collect())
+            .unwrap_or_default();
+        list.sort_by(|a, b| a.source.cmp(&b.source));
+        list
+    }
+
+    // 删除指定源文件的处理记录与失败记录，sources 为空时清空全部记录，返回删除的条数
+    pub fn forget(&self, sources: &[String]) -> Result<usize, Error> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        *state = read_state(&self.path)?;
+        let before = state.entries.len() + state.failures.len();
+        if sources.is_empty() {
+            *state = LedgerState::default();
+        } else {
+            for source in sources {
+                let key = source_key(Path::new(source));
+                for key in [source, &key] {
+                    state.entries.remove(key);
+                    state.failures.remove(key);
+                }
+            }
+        }
+        let removed = before - state.entries.len() - state.failures.len();
+        if removed > 0 {
+            self.save(&state)?;
+        }
+        Ok(removed)
+    }
+
+    // 同一输出目录可能同时被其它任务使用，也可能被 forget 清除过记录
+    // 因此以磁盘上的最新记录为准，只应用本次的修改，修改后立即保存
+    fn update(&self, change: impl FnOnce(&mut LedgerState)) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut latest = read_state(&self.path)?;
+        change(&mut latest);
+        self.save(&latest)?;
+        *state = latest;
+        Ok(())
+    }
+
+    // 先写入临时文件再重命名，避免写到一半时中断导致记录损坏
+    fn save(&self, state: &LedgerState) -> Result<(), Error> {
+        let mut entries: Vec<LedgerEntry> = state.entries.values().cloned().collect();
+        entries.sort_by(|a, b| a.source.cmp(&b.source));
+        let mut failures: Vec<FailureEntry> = state.failures.values().cloned().collect();
+        failures.sort_by(|a, b| a.source.cmp(&b.source));
+        let file = LedgerFile {
+            version: LEDGER_VERSION,
+            entries,
+            failures,
+        };
+        let content = serde_json::to_string_pretty(&file)
+            .map_err(|e| Error::invalid_data(&self.path, e.to_string()))?;
+        let tmp_path = self.path.with_extension("json.tmp");
+        fs::write(&tmp_path, content).map_err(|e| Error::io("保存处理记录", &tmp_path, e))?;
+        fs::rename(&tmp_path, &self.path).map_err(|e| Error::io("保存处理记录", &self.path, e))
+    }
+}
@@ -0,0 +1,24 @@
+// 桌面应用（main.rs）与命令行工具（bin/cli.rs）共用的核心逻辑
+
+pub mod capabilities;
+pub mod collision;
+pub mod dir_config;
+pub mod error;
+pub mod events;
+pub mod ffmpeg;
+pub mod jobs;
+pub mod ledger;
+pub mod options;
+pub mod output;
+pub mod pipeline;
+pub mod pool;
+pub mod presets;
+pub mod probe;
+pub mod progress;
+pub mod removal;
+pub mod remux;
+pub mod report;
+pub mod scan;
+pub mod settings;
+pub mod template;
+pub mod watcher;
@@ -0,0 +1,970 @@
+// 命令参数解析
+
+use crate::collision::CollisionPolicy;
+use crate::ledger::RetryPolicy;
+use crate::removal::{RemoveMode, RemovePolicy};
+use crate::remux;
+use crate::scan::{ScanOptions, SymlinkPolicy};
+use crate::template::{DateSource, PathTemplate, TemplateOptions};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// flv2mp4 命令的参数
+#[derive(Clone, Debug)]
+pub struct FlvToMp4Options {
+    pub cwd: String,
+    pub output_dir: String,
+    pub watch: bool,
+    pub archive: bool,
+    pub remove: bool,
+    // 跳过已处理过的文件，--no-skip 时重新转换所有文件并覆盖已存在的输出文件
+    pub skip: bool,
+    // -r 移除源文件前的校验与移除方式
+    pub removal: RemovePolicy,
+    pub debug: bool,
+    // 监视模式下的检查间隔（秒），轮询模式为扫描间隔，通知模式为复查未稳定文件的间隔
+    pub timeout: u64,
+    // 文件最后修改后需静置多少秒才认为录制已结束，0 表示不检查
+    pub settle_secs: u64,
+    // 监视模式下强制使用轮询，适用于收不到文件系统通知的网络共享目录
+    pub poll: bool,
+    // 文件大小需连续多少次检查保持不变，才认为录制已结束，0 表示不检查
+    pub stable_checks: u32,
+    // 同时执行转换的文件数，1 表示逐个转换
+    pub jobs: usize,
+    pub scan: ScanOptions,
+    pub retry: RetryPolicy,
+    // 输出路径模板，见 template.rs
+    pub template: TemplateOptions,
+    // 输出文件已存在时的处理方式
+    pub collision: CollisionPolicy,
+    // 预演模式：只生成执行计划，不处理任何文件
+    pub dry_run: bool,
+    // 追加在输出文件之前的 ffmpeg 参数，由目录中的 .ffmpeg-script.toml 指定
+    pub ffmpeg_args: Vec<String>,
+}
+
+// avm 命令的参数
+#[derive(Clone, Debug)]
+pub struct AvmOptions {
+    pub cwd: String,
+    // 默认为工作目录下的 audio-video-merger
+    pub output_dir: String,
+    // 音频与视频文件名中用于配对的后缀，例如 demo_audio.mp4 与 demo_video.mp4
+    pub audio_suffix: String,
+    pub video_suffix: String,
+    // 同时执行合并的文件数，1 表示逐个合并
+    pub jobs: usize,
+    pub scan: ScanOptions,
+    pub retry: RetryPolicy,
+    // 输出路径模板，见 template.rs
+    pub template: TemplateOptions,
+    // 输出文件已存在时的处理方式
+    pub collision: CollisionPolicy,
+    // 预演模式：只生成执行计划，不处理任何文件
+    pub dry_run: bool,
+    pub ffmpeg_args: Vec<String>,
+}
+
+// remux 命令的参数
+#[derive(Clone, Debug)]
+pub struct RemuxOptions {
+    pub cwd: String,
+    pub output_dir: String,
+    // 需要转换的源文件扩展名（不含点）
+    pub extensions: Vec<String>,
+    // 目标容器，同时也是输出文件的扩展名
+    pub format: String,
+    // 允许写入目标容器仅实验性支持的编码（ffmpeg 的 -strict experimental）
+    pub strict: bool,
+    pub remove: bool,
+    pub removal: RemovePolicy,
+    pub debug: bool,
+    pub jobs: usize,
+    pub scan: ScanOptions,
+    pub retry: RetryPolicy,
+    // 输出路径模板，见 template.rs
+    pub template: TemplateOptions,
+    // 输出文件已存在时的处理方式
+    pub collision: CollisionPolicy,
+    // 预演模式：只生成执行计划，不处理任何文件
+    pub dry_run: bool,
+}
+
+impl FlvToMp4Options {
+    // 输出路径模板，未指定时归档模式按 "年-月.日" 分目录
+    pub fn output_template(&self) -> Result<PathTemplate, String> {
+        let default = if self.archive {
+            "{dir}/{date:%Y-%m.%d}/{stem}.{ext}"
+        } else {
+            "{dir}/{stem}.{ext}"
+        };
+        PathTemplate::new(self.template.template.as_deref().unwrap_or(default), &self.template)
+    }
+}
+
+impl AvmOptions {
+    pub fn output_template(&self) -> Result<PathTemplate, String> {
+        PathTemplate::new(self.template.template.as_deref().unwrap_or("{dir}/{stem}.{ext}"), &self.template)
+    }
+}
+
+impl RemuxOptions {
+    pub fn output_template(&self) -> Result<PathTemplate, String> {
+        PathTemplate::new(self.template.template.as_deref().unwrap_or("{dir}/{stem}.{ext}"), &self.template)
+    }
+}
+
+// 解析 -c/-o/-w/-a/-r/-d/-t/-s/-p/-n/-j 形式的参数（均可使用 --cwd 等长选项名），以及扫描与失败重试相关的参数
+// --no-skip 不跳过已处理过的文件
+// --collision <skip|overwrite|suffix|compare> 指定输出文件已存在时的处理方式，--dry-run 只生成执行计划，三个命令相同
+pub fn parse_flv_to_mp4_args(args: &[String]) -> Result<FlvToMp4Options, String> {
+    let mut options = FlvToMp4Options {
+        cwd: std::env::current_dir()
+            .map_err(|e| format!("获取当前目录失败: {}", e))?
+            .to_string_lossy().to_string(),
+        output_dir: String::new(),
+        watch: false,
+        archive: false,
+        remove: false,
+        skip: true,
+        removal: RemovePolicy::default(),
+        debug: false,
+        timeout: 30,
+        settle_secs: 60,
+        poll: false,
+        stable_checks: 2,
+        jobs: 1,
+        scan: ScanOptions::default(),
+        retry: RetryPolicy::default(),
+        template: TemplateOptions::default(),
+        collision: CollisionPolicy::Skip,
+        dry_run: false,
+        ffmpeg_args: Vec::new(),
+    };
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "-c" | "--cwd" if i + 1 < args.len() => {
+                options.cwd = args[i + 1].clone();
+                i += 1;
+            },
+            "-o" | "--output" if i + 1 < args.len() => {
+                options.output_dir = args[i + 1].clone();
+                i += 1;
+            },
+            "-w" | "--watch" => options.watch = true,
+            "-a" | "--archive" => options.archive = true,
+            "-r" | "--remove" => options.remove = true,
+            "--no-skip" => options.skip = false,
+            "-d" | "--debug" => options.debug = true,
+            "--dry-run" => options.dry_run = true,
+            "-p" | "--poll" => options.poll = true,
+            "-t" | "--timeout" if i + 1 < args.len() => {
+                options.timeout = parse_number("--timeout", &args[i + 1])?;
+                i += 1;
+            },
+            "-s" | "--settle" if i + 1 < args.len() => {
+                options.settle_secs = parse_number("--settle", &args[i + 1])?;
+                i += 1;
+            },
+            "-n" | "--stable-checks" if i + 1 < args.len() => {
+                options.stable_checks = parse_number("--stable-checks", &args[i + 1])?;
+                i += 1;
+            },
+            "-j" | "--jobs" if i + 1 < args.len() => {
+                options.jobs = parse_number("--jobs", &args[i + 1])?;
+                i += 1;
+            },
+            "--collision" if i + 1 < args.len() => {
+                options.collision = CollisionPolicy::parse(&args[i + 1])?;
+                i += 1;
+            },
+            _ => {
+                if !parse_retry_arg(args, &mut i, &mut options.retry)?
+                    && !parse_template_arg(args, &mut i, &mut options.template)?
+                    && !parse_remove_arg(args, &mut i, &mut options.removal)? {
+                    parse_scan_arg(args, &mut i, &mut options.scan)?;
+                }
+            }
+        }
+        i += 1;
+    }
+
+    // 如果没有指定输出目录，使用默认值
+    if options.output_dir.is_empty() {
+        options.output_dir = format!("{}/flv-to-mp4", options.cwd);
+    }
+
+    // 不跳过已处理的文件时，已存在的输出文件也需要重新生成
+    if !options.skip && options.collision == CollisionPolicy::Skip {
+        options.collision = CollisionPolicy::Overwrite;
+    }
+
+    Ok(options)
+}
+
+// 解析 avm 的参数：第一个非选项参数为工作目录，-j 指定并发数
+pub fn parse_avm_args(args: &[String]) -> Result<AvmOptions, String> {
+    let mut cwd = None;
+    let mut jobs = 1;
+    let mut scan = ScanOptions::default();
+    let mut retry = RetryPolicy::default();
+    let mut template = TemplateOptions::default();
+    let mut collision = CollisionPolicy::Skip;
+    let mut dry_run = false;
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "-j" | "--jobs" if i + 1 < args.len() => {
+                jobs = parse_number("--jobs", &args[i + 1])?;
+                i += 1;
+            },
+            "--collision" if i + 1 < args.len() => {
+                collision = CollisionPolicy::parse(&args[i + 1])?;
+                i += 1;
+            },
+            "--dry-run" => dry_run = true,
+            arg if cwd.is_none() && !arg.starts_with('-') => cwd = Some(arg.to_string()),
+            _ => {
+                if !parse_retry_arg(args, &mut i, &mut retry)?
+                    && !parse_template_arg(args, &mut i, &mut template)? {
+                    parse_scan_arg(args, &mut i, &mut scan)?;
+                }
+            }
+        }
+        i += 1;
+    }
+
+    let cwd = match cwd {
+        Some(cwd) => cwd,
+        None => std::env::current_dir()
+            .map_err(|e| format!("获取当前目录失败: {}", e))?
+            .to_string_lossy().to_string(),
+    };
+
+    Ok(AvmOptions {
+        output_dir: format!("{}/audio-video-merger", cwd),
+        cwd,
+        audio_suffix: "_audio".to_string(),
+        video_suffix: "_video".to_string(),
+        jobs,
+        scan,
+        retry,
+        template,
+        collision,
+        dry_run,
+        ffmpeg_args: Vec::new(),
+    })
+}
+
+// 解析 remux 的参数：-c/-o/-r/-d/-j 与 flv2mp4 相同
+// -e <ext,...> 指定源文件扩展名（可重复），-f <mp4|mkv> 指定目标容器，--strict 允许实验性编码
+pub fn parse_remux_args(args: &[String]) -> Result<RemuxOptions, String> {
+    let mut options = RemuxOptions {
+        cwd: std::env::current_dir()
+            .map_err(|e| format!("获取当前目录失败: {}", e))?
+            .to_string_lossy().to_string(),
+        output_dir: String::new(),
+        extensions: Vec::new(),
+        format: "mp4".to_string(),
+        strict: false,
+        remove: false,
+        removal: RemovePolicy::default(),
+        debug: false,
+        jobs: 1,
+        scan: ScanOptions::default(),
+        retry: RetryPolicy::default(),
+        template: TemplateOptions::default(),
+        collision: CollisionPolicy::Skip,
+        dry_run: false,
+    };
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "-c" | "--cwd" if i + 1 < args.len() => {
+                options.cwd = args[i + 1].clone();
+                i += 1;
+            },
+            "-o" | "--output" if i + 1 < args.len() => {
+                options.output_dir = args[i + 1].clone();
+                i += 1;
+            },
+            "-e" | "--ext" if i + 1 < args.len() => {
+                let extensions = args[i + 1]
+                    .split(',')
+                    .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+                    .filter(|ext| !ext.is_empty());
+                options.extensions.extend(extensions);
+                i += 1;
+            },
+            "-f" | "--format" if i + 1 < args.len() => {
+                options.format = args[i + 1].trim_start_matches('.').to_lowercase();
+                i += 1;
+            },
+            "--strict" => options.strict = true,
+            "-r" | "--remove" => options.remove = true,
+            "-d" | "--debug" => options.debug = true,
+            "--dry-run" => options.dry_run = true,
+            "-j" | "--jobs" if i + 1 < args.len() => {
+                options.jobs = parse_number("--jobs", &args[i + 1])?;
+                i += 1;
+            },
+            "--collision" if i + 1 < args.len() => {
+                options.collision = CollisionPolicy::parse(&args[i + 1])?;
+                i += 1;
+            },
+            _ => {
+                if !parse_retry_arg(args, &mut i, &mut options.retry)?
+                    && !parse_template_arg(args, &mut i, &mut options.template)?
+                    && !parse_remove_arg(args, &mut i, &mut options.removal)? {
+                    parse_scan_arg(args, &mut i, &mut options.scan)?;
+                }
+            }
+        }
+        i += 1;
+    }
+
+    if !remux::TARGET_FORMATS.contains(&options.format.as_str()) {
+        return Err(format!(
+            "不支持的目标格式: {}（可选 {}）",
+            options.format,
+            remux::TARGET_FORMATS.join("/")
+        ));
+    }
+
+    // 没有指定源文件扩展名时，处理除目标格式外的所有常见格式
+    if options.extensions.is_empty() {
+        options.extensions = remux::SOURCE_EXTENSIONS
+            .iter()
+            .filter(|ext| **ext != options.format)
+            .map(|ext| ext.to_string())
+            .collect();
+    }
+
+    if options.output_dir.is_empty() {
+        options.output_dir = format!("{}/remux-to-{}", options.cwd, options.format);
+    }
+
+    Ok(options)
+}
+
+// 解析各命令共用的扫描参数，带值的参数会将 i 移到值的位置，其它参数也无法识别时返回错误
+// -R/--recursive、--depth <n>、--include <glob>、--exclude <glob>、--symlinks <skip|files|follow>
+fn parse_scan_arg(args: &[String], i: &mut usize, scan: &mut ScanOptions) -> Result<(), String> {
+    let value = args.get(*i + 1);
+    match (args[*i].as_str(), value) {
+        ("-R" | "--recursive", _) => scan.recursive = true,
+        ("--depth", Some(value)) => {
+            // 指定深度即表示递归扫描
+            scan.recursive = true;
+            scan.max_depth = Some(parse_number("--depth", value)?);
+            *i += 1;
+        },
+        ("--include", Some(value)) => {
+            scan.include.push(value.clone());
+            *i += 1;
+        },
+        ("--exclude", Some(value)) => {
+            scan.exclude.push(value.clone());
+            *i += 1;
+        },
+        ("--symlinks", Some(value)) => {
+            scan.symlinks = SymlinkPolicy::parse(value)?;
+            *i += 1;
+        },
+        _ => return Err(format!("无法识别的参数: {}", args[*i])),
+    }
+    Ok(())
+}
+
+// 解析各命令共用的失败重试参数，返回参数是否已被识别
+// --max-attempts <n>（0 表示不限制）、--retry-delay <秒>、--quarantine
+fn parse_retry_arg(args: &[String], i: &mut usize, retry: &mut RetryPolicy) -> Result<bool, String> {
+    let value = args.get(*i + 1);
+    match (args[*i].as_str(), value) {
+        ("--max-attempts", Some(value)) => {
+            retry.max_attempts = parse_number("--max-attempts", value)?;
+            *i += 1;
+        },
+        ("--retry-delay", Some(value)) => {
+            retry.retry_delay = parse_number("--retry-delay", value)?;
+            *i += 1;
+        },
+        ("--quarantine", _) => retry.quarantine = true,
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+// 解析各命令共用的输出路径模板参数，返回参数是否已被识别
+// --template <模板>、--date-source <mtime|ctime|name>、--name-pattern <正则表达式>
+fn parse_template_arg(args: &[String], i: &mut usize, template: &mut TemplateOptions) -> Result<bool, String> {
+    let value = args.get(*i + 1);
+    match (args[*i].as_str(), value) {
+        ("--template", Some(value)) => template.template = Some(value.clone()),
+        ("--date-source", Some(value)) => template.date_source = DateSource::parse(value)?,
+        ("--name-pattern", Some(value)) => template.name_pattern = Some(value.clone()),
+        _ => return Ok(false),
+    }
+    *i += 1;
+    Ok(true)
+}
+
+// 解析 flv2mp4 与 remux 共用的源文件移除参数，返回参数是否已被识别
+// --remove-mode <delete|trash|hold>、--hold-dir <目录>、--retention <天数>、--duration-tolerance <秒>
+fn parse_remove_arg(args: &[String], i: &mut usize, removal: &mut RemovePolicy) -> Result<bool, String> {
+    let value = args.get(*i + 1);
+    match (args[*i].as_str(), value) {
+        ("--remove-mode", Some(value)) => removal.mode = RemoveMode::parse(value)?,
+        ("--hold-dir", Some(value)) => removal.hold_dir = Some(value.clone()),
+        ("--retention", Some(value)) => removal.retention_days = parse_number("--retention", value)?,
+        ("--duration-tolerance", Some(value)) => removal.duration_tolerance = parse_number("--duration-tolerance", value)?,
+        _ => return Ok(false),
+    }
+    *i += 1;
+    Ok(true)
+}
+
+// 数值参数无法解析时返回错误，而不是悄悄使用默认值
+fn parse_number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value.trim().parse().map_err(|_| format!("{} 的值无效: {}", flag, value))
+}
+
+// 参数校验失败的原因，field 为对应的参数名（与界面传入的字段名相同）
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationError {
+    pub field: String,
+    pub message: String,
+}
+
+impl ValidationError {
+    pub fn new(field: &str, message: impl Into<String>) -> Self {
+        ValidationError { field: field.to_string(), message: message.into() }
+    }
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+// 多条校验错误合并为一条提示，用于只能返回文字的地方
+pub fn describe_errors(errors: &[ValidationError]) -> String {
+    errors.iter().map(ToString::to_string).collect::<Vec<_>>().join("；")
+}
+
+impl FlvToMp4Options {
+    // 开始处理前检查参数，返回全部错误而不只是第一个
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+        check_dirs(&self.cwd, &self.output_dir, &mut errors);
+        if self.watch && self.timeout == 0 {
+            errors.push(ValidationError::new("timeout", "监视模式的检查间隔不能为 0"));
+        }
+        check_jobs(self.jobs, &mut errors);
+        check_removal(&self.removal, &mut errors);
+        if let Err(e) = self.output_template() {
+            errors.push(ValidationError::new("template", e));
+        }
+        errors.is_empty().then_some(()).ok_or(errors)
+    }
+}
+
+impl AvmOptions {
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+        check_dirs(&self.cwd, &self.output_dir, &mut errors);
+        check_jobs(self.jobs, &mut errors);
+        if let Err(e) = self.output_template() {
+            errors.push(ValidationError::new("template", e));
+        }
+        errors.is_empty().then_some(()).ok_or(errors)
+    }
+}
+
+impl RemuxOptions {
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+        check_dirs(&self.cwd, &self.output_dir, &mut errors);
+        check_jobs(self.jobs, &mut errors);
+        check_removal(&self.removal, &mut errors);
+        if let Err(e) = self.output_template() {
+            errors.push(ValidationError::new("template", e));
+        }
+        errors.is_empty().then_some(()).ok_or(errors)
+    }
+}
+
+// 工作目录需已存在；输出目录位于工作目录内时扫描会跳过它，
+// 但输出目录是工作目录本身或其上级目录时，转换结果会被当作源文件再次扫描
+fn check_dirs(cwd: &str, output_dir: &str, errors: &mut Vec<ValidationError>) {
+    if cwd.trim().is_empty() {
+        errors.push(ValidationError::new("cwd", "请指定工作目录"));
+        return;
+    }
+    if !Path::new(cwd).is_dir() {
+        errors.push(ValidationError::new("cwd", format!("工作目录不存在：{}", cwd)));
+        return;
+    }
+    let cwd = normalize(Path::new(cwd));
+    let output = normalize(Path::new(output_dir));
+    if cwd.starts_with(&output) {
+        errors.push(ValidationError::new("output", format!("输出目录不能是工作目录或其上级目录：{}", output_dir)));
+    }
+}
+
+// 已存在的路径解析符号链接，不存在的路径只转为绝对路径
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    fs::canonicalize(path)
+        .or_else(|_| std::path::absolute(path))
+        .unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn check_jobs(jobs: usize, errors: &mut Vec<ValidationError>) {
+    if jobs == 0 {
+        errors.push(ValidationError::new("jobs", "并发数至少为 1"));
+    }
+}
+
+fn check_removal(removal: &RemovePolicy, errors: &mut Vec<ValidationError>) {
+    if removal.duration_tolerance < 0.0 {
+        errors.push(ValidationError::new("durationTolerance", "时长误差不能为负数"));
+    }
+}
+
+// 解析并校验后的命令参数
+#[derive(Clone, Debug)]
+pub enum CommandOptions {
+    FlvToMp4(FlvToMp4Options),
+    Avm(AvmOptions),
+    Remux(RemuxOptions),
+}
+
+impl CommandOptions {
+    // 解析命令行形式的参数（命令行工具与预设使用）并校验，参数格式错误记在 args 下
+    pub fn parse(command_type: &str, args: &[String]) -> Result<Self, Vec<ValidationError>> {
+        let parsed = match command_type {
+            "flv2mp4" => parse_flv_to_mp4_args(args).map(CommandOptions::FlvToMp4),
+            "avm" => parse_avm_args(args).map(CommandOptions::Avm),
+            "remux" => parse_remux_args(args).map(CommandOptions::Remux),
+            _ => return Err(vec![ValidationError::new("commandType", "未知命令类型")]),
+        };
+        let options = parsed.map_err(|e| vec![ValidationError::new("args", e)])?;
+        options.validate()?;
+        Ok(options)
+    }
+
+    pub fn command_type(&self) -> &'static str {
+        match self {
+            CommandOptions::FlvToMp4(_) => "flv2mp4",
+            CommandOptions::Avm(_) => "avm",
+            CommandOptions::Remux(_) => "remux",
+        }
+    }
+
+    pub fn cwd(&self) -> &str {
+        match self {
+            CommandOptions::FlvToMp4(options) => &options.cwd,
+            CommandOptions::Avm(options) => &options.cwd,
+            CommandOptions::Remux(options) => &options.cwd,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        match self {
+            CommandOptions::FlvToMp4(options) => options.validate(),
+            CommandOptions::Avm(options) => options.validate(),
+            CommandOptions::Remux(options) => options.validate(),
+        }
+    }
+}
+
+// 界面传入的参数，按 commandType 区分命令，未填写的项与命令行参数的默认值相同
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "commandType")]
+pub enum CommandParams {
+    #[serde(rename = "flv2mp4")]
+    FlvToMp4(FlvToMp4Params),
+    #[serde(rename = "avm")]
+    Avm(AvmParams),
+    #[serde(rename = "remux")]
+    Remux(RemuxParams),
+}
+
+impl CommandParams {
+    // 转换为命令参数并校验，返回全部错误
+    pub fn into_options(self) -> Result<CommandOptions, Vec<ValidationError>> {
+        match self {
+            CommandParams::FlvToMp4(params) => params.into_options().map(CommandOptions::FlvToMp4),
+            CommandParams::Avm(params) => params.into_options().map(CommandOptions::Avm),
+            CommandParams::Remux(params) => params.into_options().map(CommandOptions::Remux),
+        }
+    }
+
+    pub fn command_type(&self) -> &'static str {
+        match self {
+            CommandParams::FlvToMp4(_) => "flv2mp4",
+            CommandParams::Avm(_) => "avm",
+            CommandParams::Remux(_) => "remux",
+        }
+    }
+
+    pub fn cwd(&self) -> &str {
+        match self {
+            CommandParams::FlvToMp4(params) => &params.cwd,
+            CommandParams::Avm(params) => &params.cwd,
+            CommandParams::Remux(params) => &params.cwd,
+        }
+    }
+
+    pub fn cwd_mut(&mut self) -> &mut String {
+        match self {
+            CommandParams::FlvToMp4(params) => &mut params.cwd,
+            CommandParams::Avm(params) => &mut params.cwd,
+            CommandParams::Remux(params) => &mut params.cwd,
+        }
+    }
+}
+
+// 各命令共用的扫描参数
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ScanParams {
+    pub recursive: bool,
+    // 0 表示不限制
+    pub depth: usize,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub symlinks: String,
+}
+
+impl Default for ScanParams {
+    fn default() -> Self {
+        ScanParams {
+            recursive: false,
+            depth: 0,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            symlinks: "files".to_string(),
+        }
+    }
+}
+
+impl ScanParams {
+    fn to_options(&self, errors: &mut Vec<ValidationError>) -> ScanOptions {
+        ScanOptions {
+            recursive: self.recursive || self.depth > 0,
+            max_depth: (self.depth > 0).then_some(self.depth),
+            include: self.include.clone(),
+            exclude: self.exclude.clone(),
+            symlinks: collect(SymlinkPolicy::parse(&self.symlinks), "symlinks", errors).unwrap_or(SymlinkPolicy::Files),
+        }
+    }
+}
+
+// 各命令共用的失败重试、输出路径模板与冲突处理参数
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct OutputParams {
+    pub max_attempts: u32,
+    pub retry_delay: u64,
+    pub quarantine: bool,
+    pub template: Option<String>,
+    pub date_source: String,
+    pub name_pattern: Option<String>,
+    pub collision: String,
+    pub dry_run: bool,
+    pub jobs: usize,
+}
+
+impl Default for OutputParams {
+    fn default() -> Self {
+        let retry = RetryPolicy::default();
+        OutputParams {
+            max_attempts: retry.max_attempts,
+            retry_delay: retry.retry_delay,
+            quarantine: retry.quarantine,
+            template: None,
+            date_source: "mtime".to_string(),
+            name_pattern: None,
+            collision: "skip".to_string(),
+            dry_run: false,
+            jobs: 1,
+        }
+    }
+}
+
+impl OutputParams {
+    fn retry(&self) -> RetryPolicy {
+        RetryPolicy { max_attempts: self.max_attempts, retry_delay: self.retry_delay, quarantine: self.quarantine }
+    }
+
+    fn template(&self, errors: &mut Vec<ValidationError>) -> TemplateOptions {
+        TemplateOptions {
+            template: non_empty(&self.template),
+            date_source: collect(DateSource::parse(&self.date_source), "dateSource", errors).unwrap_or(DateSource::Mtime),
+            name_pattern: non_empty(&self.name_pattern),
+        }
+    }
+
+    fn collision(&self, errors: &mut Vec<ValidationError>) -> CollisionPolicy {
+        collect(CollisionPolicy::parse(&self.collision), "collision", errors).unwrap_or(CollisionPolicy::Skip)
+    }
+}
+
+// flv2mp4 与 remux 共用的源文件移除参数
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RemovalParams {
+    pub remove: bool,
+    pub remove_mode: String,
+    pub hold_dir: Option<String>,
+    pub retention: u32,
+    pub duration_tolerance: f64,
+}
+
+impl Default for RemovalParams {
+    fn default() -> Self {
+        let removal = RemovePolicy::default();
+        RemovalParams {
+            remove: false,
+            remove_mode: "trash".to_string(),
+            hold_dir: None,
+            retention: removal.retention_days,
+            duration_tolerance: removal.duration_tolerance,
+        }
+    }
+}
+
+impl RemovalParams {
+    fn to_policy(&self, errors: &mut Vec<ValidationError>) -> RemovePolicy {
+        RemovePolicy {
+            mode: collect(RemoveMode::parse(&self.remove_mode), "removeMode", errors).unwrap_or(RemoveMode::Trash),
+            hold_dir: non_empty(&self.hold_dir),
+            retention_days: self.retention,
+            duration_tolerance: self.duration_tolerance,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FlvToMp4Params {
+    pub cwd: String,
+    // 为空时为工作目录下的 flv-to-mp4
+    pub output: Option<String>,
+    pub watch: bool,
+    pub archive: bool,
+    pub skip: bool,
+    pub debug: bool,
+    pub poll: bool,
+    pub timeout: u64,
+    pub settle: u64,
+    pub stable_checks: u32,
+    #[serde(flatten)]
+    pub removal: RemovalParams,
+    #[serde(flatten)]
+    pub scan: ScanParams,
+    #[serde(flatten)]
+    pub output_params: OutputParams,
+    // 其余无法识别的参数，校验时报错而不是忽略
+    #[serde(flatten)]
+    pub unknown: BTreeMap<String, serde_json::Value>,
+}
+
+impl Default for FlvToMp4Params {
+    fn default() -> Self {
+        FlvToMp4Params {
+            cwd: String::new(),
+            output: None,
+            watch: false,
+            archive: false,
+            skip: true,
+            debug: false,
+            poll: false,
+            timeout: 30,
+            settle: 60,
+            stable_checks: 2,
+            removal: RemovalParams::default(),
+            scan: ScanParams::default(),
+            output_params: OutputParams::default(),
+            unknown: BTreeMap::new(),
+        }
+    }
+}
+
+impl FlvToMp4Params {
+    pub fn into_options(self) -> Result<FlvToMp4Options, Vec<ValidationError>> {
+        let mut errors = unknown_fields(&self.unknown);
+        let mut options = FlvToMp4Options {
+            output_dir: non_empty(&self.output).unwrap_or_else(|| format!("{}/flv-to-mp4", self.cwd)),
+            cwd: self.cwd,
+            watch: self.watch,
+            archive: self.archive,
+            remove: self.removal.remove,
+            skip: self.skip,
+            removal: self.removal.to_policy(&mut errors),
+            debug: self.debug,
+            timeout: self.timeout,
+            settle_secs: self.settle,
+            poll: self.poll,
+            stable_checks: self.stable_checks,
+            jobs: self.output_params.jobs,
+            scan: self.scan.to_options(&mut errors),
+            retry: self.output_params.retry(),
+            template: self.output_params.template(&mut errors),
+            collision: self.output_params.collision(&mut errors),
+            dry_run: self.output_params.dry_run,
+            ffmpeg_args: Vec::new(),
+        };
+        if !options.skip && options.collision == CollisionPolicy::Skip {
+            options.collision = CollisionPolicy::Overwrite;
+        }
+        finish(options, errors, FlvToMp4Options::validate)
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AvmParams {
+    pub cwd: String,
+    #[serde(flatten)]
+    pub scan: ScanParams,
+    #[serde(flatten)]
+    pub output_params: OutputParams,
+    #[serde(flatten)]
+    pub unknown: BTreeMap<String, serde_json::Value>,
+}
+
+impl AvmParams {
+    pub fn into_options(self) -> Result<AvmOptions, Vec<ValidationError>> {
+        let mut errors = unknown_fields(&self.unknown);
+        let options = AvmOptions {
+            output_dir: format!("{}/audio-video-merger", self.cwd),
+            cwd: self.cwd,
+            audio_suffix: "_audio".to_string(),
+            video_suffix: "_video".to_string(),
+            jobs: self.output_params.jobs,
+            scan: self.scan.to_options(&mut errors),
+            retry: self.output_params.retry(),
+            template: self.output_params.template(&mut errors),
+            collision: self.output_params.collision(&mut errors),
+            dry_run: self.output_params.dry_run,
+            ffmpeg_args: Vec::new(),
+        };
+        finish(options, errors, AvmOptions::validate)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RemuxParams {
+    pub cwd: String,
+    // 为空时为工作目录下的 remux-to-<format>
+    pub output: Option<String>,
+    // 为空时处理除目标格式外的所有常见格式
+    pub extensions: Vec<String>,
+    pub format: String,
+    pub strict: bool,
+    pub debug: bool,
+    #[serde(flatten)]
+    pub removal: RemovalParams,
+    #[serde(flatten)]
+    pub scan: ScanParams,
+    #[serde(flatten)]
+    pub output_params: OutputParams,
+    #[serde(flatten)]
+    pub unknown: BTreeMap<String, serde_json::Value>,
+}
+
+impl Default for RemuxParams {
+    fn default() -> Self {
+        RemuxParams {
+            cwd: String::new(),
+            output: None,
+            extensions: Vec::new(),
+            format: "mp4".to_string(),
+            strict: false,
+            debug: false,
+            removal: RemovalParams::default(),
+            scan: ScanParams::default(),
+            output_params: OutputParams::default(),
+            unknown: BTreeMap::new(),
+        }
+    }
+}
+
+impl RemuxParams {
+    pub fn into_options(self) -> Result<RemuxOptions, Vec<ValidationError>> {
+        let mut errors = unknown_fields(&self.unknown);
+        let format = self.format.trim_start_matches('.').to_lowercase();
+        if !remux::TARGET_FORMATS.contains(&format.as_str()) {
+            errors.push(ValidationError::new(
+                "format",
+                format!("不支持的目标格式: {}（可选 {}）", format, remux::TARGET_FORMATS.join("/")),
+            ));
+        }
+        let mut extensions: Vec<String> = self.extensions
+            .iter()
+            .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+            .filter(|ext| !ext.is_empty())
+            .collect();
+        if extensions.is_empty() {
+            extensions = remux::SOURCE_EXTENSIONS
+                .iter()
+                .filter(|ext| **ext != format)
+                .map(|ext| ext.to_string())
+                .collect();
+        }
+        let options = RemuxOptions {
+            output_dir: non_empty(&self.output).unwrap_or_else(|| format!("{}/remux-to-{}", self.cwd, format)),
+            cwd: self.cwd,
+            extensions,
+            format,
+            strict: self.strict,
+            remove: self.removal.remove,
+            removal: self.removal.to_policy(&mut errors),
+            debug: self.debug,
+            jobs: self.output_params.jobs,
+            scan: self.scan.to_options(&mut errors),
+            retry: self.output_params.retry(),
+            template: self.output_params.template(&mut errors),
+            collision: self.output_params.collision(&mut errors),
+            dry_run: self.output_params.dry_run,
+        };
+        finish(options, errors, RemuxOptions::validate)
+    }
+}
+
+fn unknown_fields(unknown: &BTreeMap<String, serde_json::Value>) -> Vec<ValidationError> {
+    unknown.keys().map(|field| ValidationError::new(field, "无法识别的参数")).collect()
+}
+
+// 记录取值错误，返回 None 时由调用方使用默认值继续转换，以便一次报告全部错误
+fn collect<T>(result: Result<T, String>, field: &str, errors: &mut Vec<ValidationError>) -> Option<T> {
+    result.map_err(|e| errors.push(ValidationError::new(field, e))).ok()
+}
+
+// 取值错误与参数校验的错误一起返回
+fn finish<T>(
+    options: T,
+    mut errors: Vec<ValidationError>,
+    validate: fn(&T) -> Result<(), Vec<ValidationError>>
+) -> Result<T, Vec<ValidationError>> {
+    if let Err(more) = validate(&options) {
+        errors.extend(more);
+    }
+    errors.is_empty().then_some(options).ok_or(errors)
+}
+
+fn non_empty(value: &Option<String>) -> Option<String> {
+    value.as_ref().map(|value| value.trim().to_string()).filter(|value| !value.is_empty())
+}
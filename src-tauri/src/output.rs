@@ -0,0 +1,69 @@
+// 输出文件的落盘：先写入目标目录中的临时文件，同步到磁盘后再原子地重命名为最终文件名
+// 这样目标目录中要么没有结果文件，要么是完整的结果文件，不会出现写到一半的文件
+// 临时文件与最终文件在同一目录，输出目录位于其它磁盘或网络挂载点时也不涉及跨设备重命名
+
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+
+// 与最终文件位于同一目录的临时文件，保留扩展名以便 ffmpeg 据此选择输出格式
+// 以点开头并带上进程号，不会被当作已有的结果文件，也不会与其它进程冲突
+pub fn temp_path(dest: &Path) -> PathBuf {
+    let stem = dest.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match dest.extension() {
+        Some(ext) => format!(".{}.{}.part.{}", stem, std::process::id(), ext.to_string_lossy()),
+        None => format!(".{}.{}.part", stem, std::process::id()),
+    };
+    dest.with_file_name(name)
+}
+
+// 把写完的临时文件同步到磁盘并重命名为最终文件
+// Windows 上同步文件需要可写的句柄，因此不能用 File::open
+pub fn commit(temp: &Path, dest: &Path) -> io::Result<()> {
+    OpenOptions::new().write(true).open(temp)?.sync_all()?;
+    fs::rename(temp, dest)?;
+    sync_parent(dest);
+    Ok(())
+}
+
+// 移动文件，源文件与目标位于不同的磁盘或网络挂载点时改为复制后删除源文件
+// 复制时同样先写入临时文件，保证目标位置不会出现不完整的文件
+pub fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Ok(()) => {
+            sync_parent(to);
+            Ok(())
+        },
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            let temp = temp_path(to);
+            if let Err(e) = fs::copy(from, &temp).and_then(|_| commit(&temp, to)) {
+                let _ = fs::remove_file(&temp);
+                return Err(e);
+            }
+            fs::remove_file(from)
+        },
+        Err(e) => Err(e),
+    }
+}
+
+// 把处理失败的源文件移到隔离目录，并在旁边保存同名的 .log 文件，返回移动后的位置
+pub fn quarantine(source: &Path, dir: &Path, log: &str) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let file_name = source.file_name().unwrap_or_default();
+    let dest = dir.join(file_name);
+    move_file(source, &dest)?;
+    let mut log_name = file_name.to_os_string();
+    log_name.push(".log");
+    fs::write(dir.join(log_name), log)?;
+    Ok(dest)
+}
+
+// 同步目录项，确保重命名本身也已落盘；只有 unix 支持打开目录，其它平台忽略
+fn sync_parent(path: &Path) {
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        let _ = File::open(parent).and_then(|dir| dir.sync_all());
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
@@ -0,0 +1,1338 @@
+// 各命令的处理流程：扫描文件、判断是否需要处理、调用 ffmpeg 并记录结果
+// 桌面应用与命令行工具共用，提示信息与文件事件通过 Reporter 输出，见 report.rs
+
+use crate::capabilities::{self, Requirement};
+use crate::collision::{self, CollisionPolicy, Resolution};
+use crate::dir_config;
+use crate::error::Error;
+use crate::events::{CollisionAction, FileStats, JobEvent, JobEventPayload, PlanAction, PlanEntry, SkipReason};
+use crate::ffmpeg;
+use crate::jobs::{self, JobHandle};
+use crate::ledger::{Ledger, RetryPolicy, RetryState};
+use crate::options::{AvmOptions, FlvToMp4Options, RemuxOptions, ValidationError};
+use crate::output;
+use crate::pool;
+use crate::probe;
+use crate::progress::FfmpegProgress;
+use crate::removal::{self, RemoveMode, RemovePolicy};
+use crate::remux;
+use crate::report::Reporter;
+use crate::scan::{self, ScanOptions};
+use crate::template::TemplateInput;
+use crate::watcher::{DirWatcher, StabilityTracker};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+// 检查 FFmpeg 是否已安装，返回版本信息，查找方式见 ffmpeg.rs
+pub fn check_ffmpeg_installed() -> Result<String, Error> {
+  // 重新查找，安装或更换 ffmpeg 后再次检查即可生效
+  ffmpeg::reset();
+  ffmpeg::resolve().map(|info| info.version)
+}
+
+// 发送结构化的文件事件
+fn emit_event(reporter: &dyn Reporter, job: Option<&JobHandle>, command_type: &str, event: JobEvent) {
+  reporter.event(JobEventPayload {
+      job_id: job.map(|job| job.id),
+      command_type: command_type.to_string(),
+      event,
+  });
+}
+
+// 跳过文件：发送跳过事件，预演模式下同时记入执行计划
+fn skip_file(
+  reporter: &dyn Reporter,
+  job: Option<&JobHandle>,
+  command_type: &str,
+  plan: &mut Option<Vec<PlanEntry>>,
+  file: String,
+  reason: SkipReason
+) {
+  if let Some(plan) = plan {
+      plan.push(PlanEntry {
+          file: file.clone(),
+          action: PlanAction::Skip,
+          reason: Some(reason),
+          dest: None,
+          argv: Vec::new(),
+          remove_source: false,
+      });
+  }
+  emit_event(reporter, job, command_type, JobEvent::Skipped { file, reason });
+}
+
+// 预演结束：按文件排序后逐条发送执行计划并返回
+fn finish_plan(mut plan: Vec<PlanEntry>, reporter: &dyn Reporter, job: Option<&JobHandle>, command_type: &str) -> Vec<PlanEntry> {
+  plan.sort_by(|a, b| a.file.cmp(&b.file));
+  for entry in &plan {
+      emit_event(reporter, job, command_type, JobEvent::Planned { entry: entry.clone() });
+  }
+  plan
+}
+
+// FLV 转 MP4 功能
+fn flv_to_mp4(
+  file_path: &str,
+  output_path: &Path,
+  ffmpeg_args: &[String],
+  job: Option<&JobHandle>,
+  on_progress: &mut dyn FnMut(FfmpegProgress)
+) -> Result<(), Error> {
+  let temp_path = output::temp_path(output_path);
+  let cmd = flv_to_mp4_command(Path::new(file_path), &temp_path, ffmpeg_args);
+  
+  run_ffmpeg(cmd, &temp_path, output_path, job, on_progress)
+}
+
+// ffmpeg_args 为目录配置中追加的参数
+fn flv_to_mp4_command(file_path: &Path, temp_path: &Path, ffmpeg_args: &[String]) -> Command {
+  remux_command(file_path, temp_path, &[], &[remux::output_args(false), ffmpeg_args.to_vec()].concat())
+}
+
+// 容器转换：不重新编码，把输入文件的音视频流复制到输出文件中
+fn remux_file(
+  input_path: &Path,
+  output_path: &Path,
+  input_args: &[String],
+  output_args: &[String],
+  job: Option<&JobHandle>,
+  on_progress: &mut dyn FnMut(FfmpegProgress)
+) -> Result<(), Error> {
+  let temp_path = output::temp_path(output_path);
+  let cmd = remux_command(input_path, &temp_path, input_args, output_args);
+  
+  run_ffmpeg(cmd, &temp_path, output_path, job, on_progress)
+}
+
+fn remux_command(input_path: &Path, temp_path: &Path, input_args: &[String], output_args: &[String]) -> Command {
+  let mut cmd = ffmpeg::ffmpeg_command();
+  cmd.args(["-y", "-progress", "pipe:1", "-nostats"])
+      .args(input_args)
+      .arg("-i")
+      .arg(input_path)
+      .args(output_args)
+      .arg(temp_path);
+  cmd
+}
+
+// 音视频合并功能
+fn audio_video_merger(
+  audio_file_path: &str,
+  video_file_path: &str,
+  output_path: &Path,
+  ffmpeg_args: &[String],
+  job: Option<&JobHandle>,
+  on_progress: &mut dyn FnMut(FfmpegProgress)
+) -> Result<(), Error> {
+  let temp_path = output::temp_path(output_path);
+  let cmd = audio_video_merger_command(Path::new(audio_file_path), Path::new(video_file_path), &temp_path, ffmpeg_args);
+  
+  run_ffmpeg(cmd, &temp_path, output_path, job, on_progress)
+}
+
+fn audio_video_merger_command(audio_file_path: &Path, video_file_path: &Path, temp_path: &Path, ffmpeg_args: &[String]) -> Command {
+  let mut cmd = ffmpeg::ffmpeg_command();
+  cmd.args(["-y", "-progress", "pipe:1", "-nostats", "-i"])
+      .arg(video_file_path)
+      .arg("-i")
+      .arg(audio_file_path)
+      .args(["-vcodec", "copy", "-acodec", "copy"])
+      .args(ffmpeg_args)
+      .arg(temp_path);
+  cmd
+}
+
+// 命令的完整命令行，用于预演模式的执行计划
+fn command_argv(cmd: &Command) -> Vec<String> {
+  std::iter::once(cmd.get_program())
+      .chain(cmd.get_args())
+      .map(|arg| arg.to_string_lossy().to_string())
+      .collect()
+}
+
+// 执行 ffmpeg 并等待结束，ffmpeg 写入的是目标目录中的临时文件
+// 成功后同步到磁盘并重命名为最终文件，任务被取消或执行失败时清理临时文件
+fn run_ffmpeg(
+  mut cmd: Command,
+  temp_path: &Path,
+  output_path: &Path,
+  job: Option<&JobHandle>,
+  on_progress: &mut dyn FnMut(FfmpegProgress)
+) -> Result<(), Error> {
+  let child = cmd
+      .stdin(Stdio::null())
+      .stdout(Stdio::piped())
+      .stderr(Stdio::piped())
+      .spawn()
+      .map_err(|e| match e.kind() {
+          std::io::ErrorKind::NotFound => Error::FfmpegNotFound(format!("FFmpeg 未安装: {}", cmd.get_program().to_string_lossy())),
+          _ => Error::io("执行 ffmpeg", cmd.get_program(), e),
+      })?;
+  
+  let result = match jobs::wait_child(child, job, on_progress) {
+      Ok(Some(output)) if output.status.success() => {
+          output::commit(temp_path, output_path).map_err(|e| Error::io("保存输出文件", output_path, e))
+      },
+      Ok(Some(output)) => Err(Error::ffmpeg_failed(output.status.code(), &output.stderr)),
+      Ok(None) => Err(Error::Cancelled),
+      Err(e) => Err(Error::io("等待 ffmpeg 结束", cmd.get_program(), e)),
+  };
+  
+  if result.is_err() && temp_path.exists() {
+      let _ = fs::remove_file(temp_path);
+  }
+  
+  result
+}
+
+// 把处理成功的源文件写入处理记录，写入失败只输出提示，不影响本次处理结果
+fn record_processed(ledger: &Ledger, sources: &[&Path], dest: &Path, command_type: &str, reporter: &dyn Reporter) {
+  for source in sources {
+      if let Err(e) = ledger.record(source, dest, command_type) {
+          reporter.message(&format!("[{}] 写入处理记录失败: {}", command_type, e));
+      }
+  }
+}
+
+// 隔离目录：输出目录下的 failed 目录，并保留源文件所在的子目录结构
+fn failed_dir(input_dir: &Path, output_dir: &Path, source: &Path) -> PathBuf {
+  output_dir.join("failed").join(scan::relative_parent(input_dir, source))
+}
+
+// 各命令输出信息的前缀
+pub fn tips_head(command_type: &str) -> &'static str {
+  match command_type {
+      "flv2mp4" => "[flv-to-mp4]",
+      "avm" => "[Audio-Video-Merger]",
+      _ => "[remux]",
+  }
+}
+
+// 按冲突处理方式确定输出路径，需要跳过时返回跳过的原因，覆盖或改名时输出提示并发送事件
+// 第一个源文件用于事件中的文件名，reserved 为本批次中已分配的输出路径
+fn resolve_dest(
+  policy: CollisionPolicy,
+  sources: &[&Path],
+  dest: PathBuf,
+  reserved: &HashSet<PathBuf>,
+  command_type: &str,
+  reporter: &dyn Reporter,
+  job: Option<&JobHandle>
+) -> Result<PathBuf, SkipReason> {
+  let tips_head = tips_head(command_type);
+  let file_display = sources[0].display().to_string();
+  let (msg, result) = match collision::resolve(policy, sources, dest.clone(), reserved) {
+      Resolution::Write(dest) => return Ok(dest),
+      Resolution::Overwrite(dest) => {
+          emit_event(reporter, job, command_type, JobEvent::Collision {
+              file: file_display,
+              dest: dest.display().to_string(),
+              action: CollisionAction::Overwrite,
+          });
+          (format!("{} {} 已存在，将覆盖该文件", tips_head, dest.display()), Ok(dest))
+      },
+      Resolution::Renamed(renamed) => {
+          emit_event(reporter, job, command_type, JobEvent::Collision {
+              file: file_display,
+              dest: renamed.display().to_string(),
+              action: CollisionAction::Rename,
+          });
+          (format!("{} {} 已存在，改为输出到 {}", tips_head, dest.display(), renamed.display()), Ok(renamed))
+      },
+      Resolution::Exists => {
+          (format!("{} {} 已存在，跳过", tips_head, dest.display()), Err(SkipReason::OutputExists))
+      },
+      Resolution::Identical => {
+          (format!("{} {} 已存在且与源文件内容相同，跳过", tips_head, dest.display()), Err(SkipReason::IdenticalOutput))
+      },
+  };
+  reporter.message(&msg);
+  result
+}
+
+// 记录处理失败的文件，第一个源文件作为失败计数的依据
+// 达到最大尝试次数后不再重试，开启隔离时把源文件与错误输出移到隔离目录
+fn record_failure(
+  retry: &RetryPolicy,
+  ledger: &Ledger,
+  sources: &[&Path],
+  failed_dir: &Path,
+  stderr: &str,
+  tips_head: &str,
+  reporter: &dyn Reporter
+) {
+  let source = sources[0];
+  let file_name = source.file_name().unwrap_or_default().to_string_lossy();
+  let (msg, exhausted) = match ledger.record_failure(source, stderr) {
+      Ok(attempts) if retry.exhausted(attempts) => {
+          (format!("{} {} 已失败 {} 次，不再重试", tips_head, file_name, attempts), true)
+      },
+      Ok(attempts) => (format!("{} {} 第 {} 次失败，稍后重试", tips_head, file_name, attempts), false),
+      Err(e) => (format!("{} 写入失败记录失败: {}", tips_head, e), false),
+  };
+  reporter.message(&msg);
+  
+  if !retry.quarantine || !exhausted {
+      return;
+  }
+  
+  for source in sources {
+      let msg = match output::quarantine(source, failed_dir, stderr) {
+          Ok(moved_to) => {
+              let _ = ledger.mark_quarantined(source, &moved_to);
+              format!("{} 已将 {} 移到 {}", tips_head, source.display(), moved_to.display())
+          },
+          Err(e) => format!("{} 移动 {} 到隔离目录失败: {}", tips_head, source.display(), e),
+      };
+      reporter.message(&msg);
+  }
+}
+
+// 校验输出文件后按设置移除源文件，第一个源文件用于校验
+// 校验未通过时保留全部源文件并返回原因，移除失败只输出提示
+fn remove_sources(
+  removal: &RemovePolicy,
+  sources: &[PathBuf],
+  dest: &Path,
+  input_dir: &Path,
+  hold_dir: &Path,
+  tips_head: &str,
+  reporter: &dyn Reporter
+) -> Result<(), String> {
+  if let Err(reason) = removal::verify_output(&sources[0], dest, removal.duration_tolerance) {
+      reporter.message(&format!("{} {} 未通过校验，已保留源文件：{}", tips_head, dest.display(), reason));
+      return Err(reason);
+  }
+  
+  for source in sources {
+      let msg = match removal::remove_source(source, removal.mode, input_dir, hold_dir) {
+          Ok(Some(moved_to)) => format!("{} 已将源文件 {} 移到 {}", tips_head, source.display(), moved_to.display()),
+          Ok(None) if removal.mode == RemoveMode::Trash => format!("{} 已将源文件 {} 移到回收站", tips_head, source.display()),
+          Ok(None) => format!("{} 已删除源文件 {}", tips_head, source.display()),
+          Err(e) => format!("{} {}: {}", tips_head, e, source.display()),
+      };
+      reporter.message(&msg);
+  }
+  Ok(())
+}
+
+// 清理暂存目录中超过保留天数的源文件，只在移除方式为暂存时执行
+fn purge_held_sources(removal: &RemovePolicy, hold_dir: &Path, tips_head: &str, reporter: &dyn Reporter) {
+  if removal.mode != RemoveMode::Hold {
+      return;
+  }
+  let msg = match removal::purge_expired(hold_dir, removal.retention_days) {
+      Ok(0) => return,
+      Ok(removed) => format!("{} 已清理暂存目录中 {} 天前的 {} 个日期目录", tips_head, removal.retention_days, removed),
+      Err(e) => format!("{} {}", tips_head, e),
+  };
+  reporter.message(&msg);
+}
+
+// 模板在校验参数时已检查过，这里出错时同样作为参数错误返回
+fn invalid_template(message: String) -> Error {
+  Error::Validation(vec![ValidationError::new("template", message)])
+}
+
+// 流检查未通过的原因
+enum StreamProblem {
+  // 文件中没有所需的流，跳过该文件
+  Missing(String),
+  // ffprobe 无法读取文件，多为损坏的文件，与 ffmpeg 执行失败一样记入失败记录
+  Unreadable(String),
+}
+
+// 通过 ffprobe 检查文件中是否包含所需的流
+// 两者都不要求时只检查文件中至少有一个音频或视频流，ffprobe 不可用时不做检查
+fn check_streams(path: &Path, need_audio: bool, need_video: bool) -> Result<(), StreamProblem> {
+  if !probe::is_available() {
+      return Ok(());
+  }
+  
+  let info = probe::probe_media(path).map_err(|e| StreamProblem::Unreadable(e.to_string()))?;
+  
+  let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+  if need_audio && !info.has_audio() {
+      Err(StreamProblem::Missing(format!("{} 中没有音频流", file_name)))
+  } else if need_video && !info.has_video() {
+      Err(StreamProblem::Missing(format!("{} 中没有视频流", file_name)))
+  } else if !info.has_audio() && !info.has_video() {
+      Err(StreamProblem::Missing(format!("{} 中没有音视频流", file_name)))
+  } else {
+      Ok(())
+  }
+}
+
+// 处理 FLV 转 MP4 的主要逻辑，预演模式下返回执行计划，否则返回空列表
+// 监视模式下传入 stability，用于在多次扫描之间跟踪文件大小是否已稳定
+pub fn handle_flv_to_mp4(
+  options: &FlvToMp4Options,
+  reporter: &dyn Reporter,
+  job: Option<&JobHandle>,
+  stats: &mut FileStats,
+  mut stability: Option<&mut StabilityTracker>
+) -> Result<Vec<PlanEntry>, Error> {
+  // 工作目录中的 .ffmpeg-script.toml 覆盖传入的参数
+  let options = &dir_config::flv_to_mp4_options(options)?;
+  let input_dir = Path::new(&options.cwd);
+  let output_dir = Path::new(&options.output_dir);
+  
+  // 确保输出目录存在，预演模式不创建任何目录
+  if !output_dir.exists() && !options.dry_run {
+      fs::create_dir_all(output_dir).map_err(|e| Error::io("创建输出目录", output_dir, e))?;
+      reporter.message(&format!("[flv-to-mp4] 转换结果存放目录创建成功：{}", output_dir.display()));
+  }
+  
+  // 暂存目录中的源文件超过保留天数后删除
+  let hold_dir = options.removal.hold_dir(output_dir);
+  if options.remove && !options.dry_run {
+      purge_held_sources(&options.removal, &hold_dir, "[flv-to-mp4]", reporter);
+  }
+  
+  // 获取所有 flv 文件，输出目录与暂存目录位于工作目录内时不扫描这两个目录
+  let flv_files = scan::scan_files(input_dir, &options.scan, &[output_dir, &hold_dir], |path| {
+      path.extension().is_some_and(|ext| ext == "flv")
+  })?;
+  
+  if flv_files.is_empty() {
+      reporter.message(&format!("[flv-to-mp4] {} 当前目录下未发现flv文件", input_dir.display()));
+      return Ok(Vec::new());
+  }
+  
+  // 开始处理前确认 ffmpeg 支持所需的格式
+  let mut requirements = capabilities::format_requirements(&flv_files, false);
+  requirements.push(Requirement::Muxer("mp4"));
+  capabilities::require(&requirements)?;
+  
+  for flv_file in &flv_files {
+      emit_event(reporter, job, "flv2mp4", JobEvent::FileDiscovered { file: flv_file.display().to_string() });
+  }
+  
+  let ledger = Ledger::open(output_dir)?;
+  let template = options.output_template().map_err(invalid_template)?;
+  
+  // 获取输出目录（包括归档子目录）中已转换的 mp4 文件，预演模式下输出目录可能还不存在
+  let all_levels = ScanOptions { recursive: true, ..ScanOptions::default() };
+  let mp4_files = if output_dir.exists() {
+      scan::scan_files(output_dir, &all_levels, &[], |path| {
+          path.extension().is_some_and(|ext| ext == "mp4")
+      })?
+  } else {
+      Vec::new()
+  };
+  
+  if let Some(stability) = stability.as_deref_mut() {
+      stability.prune();
+  }
+  
+  // 逐个检查 flv 文件，筛选出需要转换的文件
+  let total = flv_files.len();
+  let processed = AtomicUsize::new(0);
+  let report_progress = |file: &str| {
+      let current = processed.fetch_add(1, Ordering::SeqCst) + 1;
+      emit_event(reporter, job, "flv2mp4", JobEvent::Progress { file: file.to_string(), current, total });
+  };
+  
+  let mut pending_files = Vec::new();
+  let mut reserved = HashSet::new();
+  let mut plan = options.dry_run.then(Vec::new);
+  for flv_file in flv_files {
+      jobs::check_cancelled(job)?;
+      
+      let file_name = flv_file.file_stem().ok_or_else(|| Error::InvalidPath(flv_file.clone()))?
+          .to_string_lossy().to_string();
+      let file_display = flv_file.display().to_string();
+      
+      // 处理记录中已有该文件，说明转换后已被归档或移走，--no-skip 时重新转换
+      if options.skip && ledger.contains(&flv_file) {
+          if options.debug {
+              reporter.message(&format!("[flv-to-mp4] {}已转换过，跳过", file_name));
+          }
+          report_progress(&file_display);
+          skip_file(reporter, job, "flv2mp4", &mut plan, file_display, SkipReason::AlreadyProcessed);
+          stats.skipped += 1;
+          continue;
+      }
+      
+      // 上次转换失败的文件按退避时间重试，失败次数达到上限后不再处理
+      match ledger.retry_state(&flv_file, &options.retry) {
+          RetryState::Ready => {},
+          RetryState::Waiting(remaining) => {
+              if options.debug {
+                  reporter.message(&format!("[flv-to-mp4] {} 上次处理失败，{} 秒后重试", file_name, remaining.as_secs()));
+              }
+              if let Some(stability) = stability.as_deref_mut() {
+                  stability.defer(&flv_file);
+              }
+              report_progress(&file_display);
+              skip_file(reporter, job, "flv2mp4", &mut plan, file_display, SkipReason::RetryLater);
+              stats.skipped += 1;
+              continue;
+          },
+          RetryState::Exhausted(attempts) => {
+              if options.debug {
+                  reporter.message(&format!("[flv-to-mp4] {} 已失败 {} 次，跳过", file_name, attempts));
+              }
+              report_progress(&file_display);
+              skip_file(reporter, job, "flv2mp4", &mut plan, file_display, SkipReason::TooManyFailures);
+              stats.skipped += 1;
+              continue;
+          }
+      }
+      
+      let dest_path = match template.render(&TemplateInput {
+          source: &flv_file,
+          root: input_dir,
+          stem: &file_name,
+          ext: "mp4",
+      }) {
+          Ok(relative) => output_dir.join(relative),
+          Err(e) => {
+              reporter.message(&format!("[flv-to-mp4] {}", e));
+              report_progress(&file_display);
+              skip_file(reporter, job, "flv2mp4", &mut plan, file_display, SkipReason::InvalidDestination);
+              stats.skipped += 1;
+              continue;
+          }
+      };
+      
+      // 使用默认布局与默认的冲突处理方式时，对应的输出子目录下存在同名的 mp4 文件（如已归档到其它日期的目录）也视为已转换
+      let dest_dir = output_dir.join(scan::relative_parent(input_dir, &flv_file));
+      let converted = options.collision == CollisionPolicy::Skip && options.template.template.is_none()
+          && mp4_files.iter().any(|mp4_file| {
+              mp4_file.starts_with(&dest_dir) && mp4_file.file_stem() == flv_file.file_stem()
+          });
+      if converted {
+          reporter.message(&format!("[flv-to-mp4] {}的mp4版本的文件已存在", file_name));
+          report_progress(&file_display);
+          skip_file(reporter, job, "flv2mp4", &mut plan, file_display, SkipReason::OutputExists);
+          stats.skipped += 1;
+          continue;
+      }
+      
+      // 如果是监视模式，检查文件是否正在被修改
+      if options.watch {
+          if let Ok(metadata) = fs::metadata(&flv_file) {
+              // 文件大小需在连续多次检查中保持不变
+              let size_changing = stability.as_deref_mut()
+                  .is_some_and(|stability| !stability.check(&flv_file, metadata.len()));
+              if size_changing {
+                  if options.debug {
+                      reporter.message(&format!("[flv-to-mp4] {} 文件大小仍在变化，可能还未录制结束，暂时跳过", file_name));
+                  }
+                  report_progress(&file_display);
+                  skip_file(reporter, job, "flv2mp4", &mut plan, file_display, SkipReason::StillRecording);
+                  stats.skipped += 1;
+                  continue;
+              }
+              
+              if let Ok(modified) = metadata.modified() {
+                  let now = std::time::SystemTime::now();
+                  if let Ok(duration) = now.duration_since(modified) {
+                      if duration.as_secs() < options.settle_secs {
+                          if options.debug {
+                              reporter.message(&format!("[flv-to-mp4] {} 文件内容最近仍在修改，可能还未录制结束，暂时跳过", file_name));
+                          }
+                          if let Some(stability) = stability.as_deref_mut() {
+                              stability.defer(&flv_file);
+                          }
+                          report_progress(&file_display);
+                          skip_file(reporter, job, "flv2mp4", &mut plan, file_display, SkipReason::StillRecording);
+                          stats.skipped += 1;
+                          continue;
+                      }
+                  }
+              }
+          }
+      }
+      
+      // 确认文件中确实包含音视频流，避免把损坏或空的文件交给 ffmpeg
+      match check_streams(&flv_file, false, false) {
+          Ok(()) => {},
+          Err(StreamProblem::Missing(reason)) => {
+              reporter.message(&format!("[flv-to-mp4] {}，跳过", reason));
+              report_progress(&file_display);
+              skip_file(reporter, job, "flv2mp4", &mut plan, file_display, SkipReason::MissingStream);
+              stats.skipped += 1;
+              continue;
+          },
+          // 无法读取的文件计入失败次数，按退避时间重试，达到上限后不再处理或移到隔离目录
+          Err(StreamProblem::Unreadable(e)) => {
+              reporter.message(&format!("[flv-to-mp4] {}", e));
+              report_progress(&file_display);
+              if plan.is_some() {
+                  skip_file(reporter, job, "flv2mp4", &mut plan, file_display, SkipReason::UnreadableMedia);
+                  stats.skipped += 1;
+              } else {
+                  emit_event(reporter, job, "flv2mp4", JobEvent::Failed { file: file_display, stderr: e.clone() });
+                  let failed_dir = failed_dir(input_dir, output_dir, &flv_file);
+                  record_failure(&options.retry, &ledger, &[&flv_file], &failed_dir, &e, "[flv-to-mp4]", reporter);
+                  stats.failed += 1;
+              }
+              continue;
+          }
+      }
+      
+      // 目标文件已存在时按冲突处理方式跳过、覆盖或改名
+      let dest_path = match resolve_dest(options.collision, &[&flv_file], dest_path, &reserved, "flv2mp4", reporter, job) {
+          Ok(dest_path) => dest_path,
+          Err(reason) => {
+              report_progress(&file_display);
+              skip_file(reporter, job, "flv2mp4", &mut plan, file_display, reason);
+              stats.skipped += 1;
+              continue;
+          }
+      };
+      
+      reserved.insert(dest_path.clone());
+      pending_files.push((flv_file, dest_path));
+  }
+  
+  // 预演模式只生成执行计划，不执行转换
+  if let Some(mut plan) = plan {
+      for (flv_file, dest_path) in &pending_files {
+          plan.push(PlanEntry {
+              file: flv_file.display().to_string(),
+              action: PlanAction::Process,
+              reason: None,
+              dest: Some(dest_path.display().to_string()),
+              argv: command_argv(&flv_to_mp4_command(flv_file, &output::temp_path(dest_path), &options.ffmpeg_args)),
+              remove_source: options.remove,
+          });
+      }
+      return Ok(finish_plan(plan, reporter, job, "flv2mp4"));
+  }
+  
+  // 按指定的并发数执行转换，默认逐个执行
+  let stats = Mutex::new(stats);
+  pool::run(pending_files, options.jobs, |(flv_file, dest_path)| {
+      jobs::check_cancelled(job)?;
+      report_progress(&flv_file.display().to_string());
+      
+      let converted = convert_flv_file(options, &flv_file, &dest_path, &ledger, reporter, job)?;
+      if let Ok(mut stats) = stats.lock() {
+          if converted {
+              stats.converted += 1;
+          } else {
+              stats.failed += 1;
+          }
+      }
+      Ok(())
+  })?;
+  Ok(Vec::new())
+}
+
+// 转换单个 flv 文件并移动到输出目录，返回是否转换成功
+// 单个文件转换失败不影响其它文件，只有任务被取消或文件操作出错时才返回 Err
+fn convert_flv_file(
+  options: &FlvToMp4Options,
+  flv_file: &Path,
+  dest_path: &Path,
+  ledger: &Ledger,
+  reporter: &dyn Reporter,
+  job: Option<&JobHandle>
+) -> Result<bool, Error> {
+  let file_name = flv_file.file_stem().ok_or_else(|| Error::InvalidPath(flv_file.to_path_buf()))?
+      .to_string_lossy().to_string();
+  let file_display = flv_file.display().to_string();
+  
+  // 开始转换
+  reporter.message(&format!("[flv-to-mp4] 正在转换：{}", flv_file.display()));
+  emit_event(reporter, job, "flv2mp4", JobEvent::Started { file: file_display.clone() });
+  
+  if let Some(parent) = dest_path.parent() {
+      fs::create_dir_all(parent).map_err(|e| Error::io("创建目标目录", parent, e))?;
+  }
+  
+  let start_time = std::time::Instant::now();
+  
+  let mut on_progress = |progress: FfmpegProgress| {
+      emit_event(reporter, job, "flv2mp4", JobEvent::FileProgress { file: file_display.clone(), progress });
+  };
+  
+  // 直接写入目标目录，输出目录位于其它磁盘时也不需要跨设备移动
+  match flv_to_mp4(flv_file.to_str().ok_or_else(|| Error::InvalidPath(flv_file.to_path_buf()))?, dest_path, &options.ffmpeg_args, job, &mut on_progress) {
+      Ok(_) => {
+          let duration = start_time.elapsed().as_secs_f32();
+          reporter.message(&format!("[flv-to-mp4] {} 转换成功，耗时：{:.2}s", file_name, duration));
+          emit_event(reporter, job, "flv2mp4", JobEvent::Succeeded {
+              file: file_display.clone(),
+              elapsed: duration as f64,
+              dest: dest_path.display().to_string(),
+          });
+          
+          record_processed(ledger, &[flv_file], dest_path, "flv2mp4", reporter);
+          
+          // 如果需要删除源文件，先确认输出文件完整
+          if options.remove {
+              let output_dir = Path::new(&options.output_dir);
+              let removed = remove_sources(
+                  &options.removal, &[flv_file.to_path_buf()], dest_path, Path::new(&options.cwd),
+                  &options.removal.hold_dir(output_dir), "[flv-to-mp4]", reporter
+              );
+              if let Err(reason) = removed {
+                  emit_event(reporter, job, "flv2mp4", JobEvent::VerifyFailed { file: file_display, reason });
+              }
+          }
+          
+          Ok(true)
+      },
+      Err(e) => {
+          jobs::check_cancelled(job)?;
+          let e = e.to_string();
+          reporter.message(&format!("[flv-to-mp4] {}转换失败：\n{}", file_name, e));
+          emit_event(reporter, job, "flv2mp4", JobEvent::Failed { file: file_display, stderr: e.clone() });
+          
+          let failed_dir = failed_dir(Path::new(&options.cwd), Path::new(&options.output_dir), flv_file);
+          record_failure(&options.retry, ledger, &[flv_file], &failed_dir, &e, "[flv-to-mp4]", reporter);
+          
+          Ok(false)
+      }
+  }
+}
+
+// 一组待合并的音视频文件
+struct MergePair {
+  audio_file: PathBuf,
+  video_file: PathBuf,
+  // 去掉音频后缀的基础文件名
+  name: String,
+  result_path: PathBuf,
+}
+
+// 处理音视频合并的主要逻辑，预演模式下返回执行计划，否则返回空列表
+pub fn handle_audio_video_merger(
+  options: &AvmOptions,
+  reporter: &dyn Reporter,
+  job: Option<&JobHandle>,
+  stats: &mut FileStats
+) -> Result<Vec<PlanEntry>, Error> {
+  // 工作目录中的 .ffmpeg-script.toml 覆盖传入的参数
+  let options = &dir_config::avm_options(options)?;
+  let cwd_path = Path::new(&options.cwd);
+  let output_dir = PathBuf::from(&options.output_dir);
+  
+  // 确保输出目录存在，预演模式不创建任何目录
+  if !output_dir.exists() && !options.dry_run {
+      fs::create_dir_all(&output_dir).map_err(|e| Error::io("创建输出目录", &output_dir, e))?;
+  }
+  
+  let ledger = Ledger::open(&output_dir)?;
+  let template = options.output_template().map_err(invalid_template)?;
+  
+  // 获取所有音频和视频文件，不扫描输出目录
+  let mut audio_files = Vec::new();
+  let mut video_files = Vec::new();
+  
+  for path in scan::scan_files(cwd_path, &options.scan, &[&output_dir], |_| true)? {
+      let file_name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+      if file_name.contains(&format!("{}.", options.audio_suffix)) {
+          audio_files.push(path);
+      } else if file_name.contains(&format!("{}.", options.video_suffix)) {
+          video_files.push(path);
+      }
+  }
+  
+  if audio_files.is_empty() {
+      reporter.message(&format!("[Audio-Video-Merger] {} 当前目录下未发现可合并的音视频文件", cwd_path.display()));
+      return Ok(Vec::new());
+  }
+  
+  // 输出文件与视频文件的格式相同
+  let mut requirements = capabilities::format_requirements(&audio_files, false);
+  requirements.extend(capabilities::format_requirements(&video_files, true));
+  capabilities::require(&requirements)?;
+  
+  for audio_file in &audio_files {
+      emit_event(reporter, job, "avm", JobEvent::FileDiscovered { file: audio_file.display().to_string() });
+  }
+  
+  // 逐个为音频文件查找对应的视频文件，筛选出需要合并的文件
+  let total = audio_files.len();
+  let processed = AtomicUsize::new(0);
+  let report_progress = |file: &str| {
+      let current = processed.fetch_add(1, Ordering::SeqCst) + 1;
+      emit_event(reporter, job, "avm", JobEvent::Progress { file: file.to_string(), current, total });
+  };
+  
+  let mut pairs: Vec<MergePair> = Vec::new();
+  let mut reserved = HashSet::new();
+  let mut plan = options.dry_run.then(Vec::new);
+  for audio_file in audio_files {
+      jobs::check_cancelled(job)?;
+      
+      let file_info = audio_file.file_stem().ok_or_else(|| Error::InvalidPath(audio_file.clone()))?
+          .to_string_lossy().to_string();
+      let audio_file_name = file_info.replace(&options.audio_suffix, "");
+      let file_display = audio_file.display().to_string();
+      
+      // 查找同一目录下匹配的视频文件
+      let mut matching_video_files = Vec::new();
+      for video_file in &video_files {
+          let video_file_name = video_file.file_name().unwrap_or_default().to_string_lossy().to_string();
+          if video_file.parent() == audio_file.parent()
+              && video_file_name.contains(&format!("{}{}.", audio_file_name, options.video_suffix)) {
+              matching_video_files.push(video_file.clone());
+          }
+      }
+      
+      if matching_video_files.is_empty() {
+          reporter.message(&format!("[Audio-Video-Merger] 未找到【{}】对应的视频文件", audio_file.display()));
+          report_progress(&file_display);
+          skip_file(reporter, job, "avm", &mut plan, file_display, SkipReason::NoMatchingVideo);
+          stats.skipped += 1;
+          continue;
+      }
+      
+      let video_file = matching_video_files.swap_remove(0);
+      let video_file_info = video_file.file_name().ok_or_else(|| Error::InvalidPath(video_file.clone()))?
+          .to_string_lossy().to_string();
+      let result_video_file_name = PathBuf::from(video_file_info.replace(&options.video_suffix, ""));
+      let result_video_file_path = match template.render(&TemplateInput {
+          source: &video_file,
+          root: cwd_path,
+          stem: &result_video_file_name.file_stem().unwrap_or_default().to_string_lossy(),
+          ext: &result_video_file_name.extension().unwrap_or_default().to_string_lossy(),
+      }) {
+          Ok(relative) => output_dir.join(relative),
+          Err(e) => {
+              reporter.message(&format!("[Audio-Video-Merger] {}", e));
+              report_progress(&file_display);
+              skip_file(reporter, job, "avm", &mut plan, file_display, SkipReason::InvalidDestination);
+              stats.skipped += 1;
+              continue;
+          }
+      };
+      
+      // 处理记录中已有这组音视频文件，说明合并结果已被移走
+      if ledger.contains(&audio_file) && ledger.contains(&video_file) {
+          report_progress(&file_display);
+          skip_file(reporter, job, "avm", &mut plan, file_display, SkipReason::AlreadyProcessed);
+          stats.skipped += 1;
+          continue;
+      }
+      
+      // 上次合并失败的文件按退避时间重试，失败次数达到上限后不再处理
+      match ledger.retry_state(&audio_file, &options.retry) {
+          RetryState::Ready => {},
+          RetryState::Waiting(_) => {
+              report_progress(&file_display);
+              skip_file(reporter, job, "avm", &mut plan, file_display, SkipReason::RetryLater);
+              stats.skipped += 1;
+              continue;
+          },
+          RetryState::Exhausted(_) => {
+              report_progress(&file_display);
+              skip_file(reporter, job, "avm", &mut plan, file_display, SkipReason::TooManyFailures);
+              stats.skipped += 1;
+              continue;
+          }
+      }
+      
+      // 确认音频文件中有音频流、视频文件中有视频流
+      let checked = check_streams(&audio_file, true, false)
+          .and_then(|_| check_streams(&video_file, false, true));
+      match checked {
+          Ok(()) => {},
+          Err(StreamProblem::Missing(reason)) => {
+              reporter.message(&format!("[Audio-Video-Merger] 【{}】{}，跳过", audio_file_name, reason));
+              report_progress(&file_display);
+              skip_file(reporter, job, "avm", &mut plan, file_display, SkipReason::MissingStream);
+              stats.skipped += 1;
+              continue;
+          },
+          // 与合并失败相同，失败次数记在音频文件上
+          Err(StreamProblem::Unreadable(e)) => {
+              reporter.message(&format!("[Audio-Video-Merger] 【{}】{}", audio_file_name, e));
+              report_progress(&file_display);
+              if plan.is_some() {
+                  skip_file(reporter, job, "avm", &mut plan, file_display, SkipReason::UnreadableMedia);
+                  stats.skipped += 1;
+              } else {
+                  emit_event(reporter, job, "avm", JobEvent::Failed { file: file_display, stderr: e.clone() });
+                  let failed_dir = failed_dir(cwd_path, &output_dir, &audio_file);
+                  record_failure(&options.retry, &ledger, &[&audio_file, &video_file], &failed_dir, &e, "[Audio-Video-Merger]", reporter);
+                  stats.failed += 1;
+              }
+              continue;
+          }
+      }
+      
+      // 输出文件已存在，或已有其它音频文件会合并到同一个输出文件时，按冲突处理方式跳过、覆盖或改名
+      let result_video_file_path = match resolve_dest(
+          options.collision, &[&audio_file, &video_file], result_video_file_path, &reserved, "avm", reporter, job
+      ) {
+          Ok(result_path) => result_path,
+          Err(reason) => {
+              report_progress(&file_display);
+              skip_file(reporter, job, "avm", &mut plan, file_display, reason);
+              stats.skipped += 1;
+              continue;
+          }
+      };
+      
+      reserved.insert(result_video_file_path.clone());
+      pairs.push(MergePair {
+          audio_file,
+          video_file,
+          name: audio_file_name,
+          result_path: result_video_file_path,
+      });
+  }
+  
+  // 预演模式只生成执行计划，不执行合并
+  if let Some(mut plan) = plan {
+      for pair in &pairs {
+          let cmd = audio_video_merger_command(
+              &pair.audio_file, &pair.video_file, &output::temp_path(&pair.result_path), &options.ffmpeg_args
+          );
+          plan.push(PlanEntry {
+              file: pair.audio_file.display().to_string(),
+              action: PlanAction::Process,
+              reason: None,
+              dest: Some(pair.result_path.display().to_string()),
+              argv: command_argv(&cmd),
+              remove_source: false,
+          });
+      }
+      return Ok(finish_plan(plan, reporter, job, "avm"));
+  }
+  
+  // 按指定的并发数执行合并，默认逐个执行
+  let stats = Mutex::new(stats);
+  pool::run(pairs, options.jobs, |pair| {
+      jobs::check_cancelled(job)?;
+      report_progress(&pair.audio_file.display().to_string());
+      
+      let merged = merge_pair(options, &pair, &ledger, reporter, job)?;
+      if let Ok(mut stats) = stats.lock() {
+          if merged {
+              stats.converted += 1;
+          } else {
+              stats.failed += 1;
+          }
+      }
+      Ok(())
+  })?;
+  Ok(Vec::new())
+}
+
+// 合并一组音视频文件并移动到输出目录，返回是否合并成功
+fn merge_pair(
+  options: &AvmOptions,
+  pair: &MergePair,
+  ledger: &Ledger,
+  reporter: &dyn Reporter,
+  job: Option<&JobHandle>
+) -> Result<bool, Error> {
+  let start_time = std::time::Instant::now();
+  let file_display = pair.audio_file.display().to_string();
+  
+  // 开始合并
+  reporter.message(&format!("[Audio-Video-Merger] 正在合并：{}", pair.name));
+  emit_event(reporter, job, "avm", JobEvent::Started { file: file_display.clone() });
+  
+  if let Some(parent) = pair.result_path.parent() {
+      fs::create_dir_all(parent).map_err(|e| Error::io("创建目标目录", parent, e))?;
+  }
+  
+  let mut on_progress = |progress: FfmpegProgress| {
+      emit_event(reporter, job, "avm", JobEvent::FileProgress { file: file_display.clone(), progress });
+  };
+  
+  match audio_video_merger(
+      pair.audio_file.to_str().ok_or_else(|| Error::InvalidPath(pair.audio_file.clone()))?,
+      pair.video_file.to_str().ok_or_else(|| Error::InvalidPath(pair.video_file.clone()))?,
+      &pair.result_path,
+      &options.ffmpeg_args,
+      job,
+      &mut on_progress
+  ) {
+      Ok(_) => {
+          let duration = start_time.elapsed().as_secs_f32();
+          reporter.message(&format!("[Audio-Video-Merger] {} 合并成功，耗时：{:.2}s", pair.name, duration));
+          emit_event(reporter, job, "avm", JobEvent::Succeeded {
+              file: file_display,
+              elapsed: duration as f64,
+              dest: pair.result_path.display().to_string(),
+          });
+          
+          record_processed(ledger, &[&pair.audio_file, &pair.video_file], &pair.result_path, "avm", reporter);
+          
+          Ok(true)
+      },
+      Err(e) => {
+          jobs::check_cancelled(job)?;
+          let e = e.to_string();
+          reporter.message(&format!("[Audio-Video-Merger] {}合并失败：\n{}", pair.name, e));
+          emit_event(reporter, job, "avm", JobEvent::Failed { file: file_display, stderr: e.clone() });
+          
+          let cwd_path = Path::new(&options.cwd);
+          let failed_dir = failed_dir(cwd_path, Path::new(&options.output_dir), &pair.audio_file);
+          record_failure(
+              &options.retry, ledger, &[&pair.audio_file, &pair.video_file],
+              &failed_dir, &e, "[Audio-Video-Merger]", reporter
+          );
+          
+          Ok(false)
+      }
+  }
+}
+
+// 处理通用容器转换的主要逻辑，预演模式下返回执行计划，否则返回空列表
+pub fn handle_remux(
+  options: &RemuxOptions,
+  reporter: &dyn Reporter,
+  job: Option<&JobHandle>,
+  stats: &mut FileStats
+) -> Result<Vec<PlanEntry>, Error> {
+  let input_dir = Path::new(&options.cwd);
+  let output_dir = Path::new(&options.output_dir);
+  
+  // 确保输出目录存在，预演模式不创建任何目录
+  if !output_dir.exists() && !options.dry_run {
+      fs::create_dir_all(output_dir).map_err(|e| Error::io("创建输出目录", output_dir, e))?;
+      reporter.message(&format!("[remux] 转换结果存放目录创建成功：{}", output_dir.display()));
+  }
+  
+  // 暂存目录中的源文件超过保留天数后删除
+  let hold_dir = options.removal.hold_dir(output_dir);
+  if options.remove && !options.dry_run {
+      purge_held_sources(&options.removal, &hold_dir, "[remux]", reporter);
+  }
+  
+  // 获取所有指定扩展名的文件，不扫描输出目录与暂存目录
+  let files = scan::scan_files(input_dir, &options.scan, &[output_dir, &hold_dir], |path| {
+      path.extension().is_some_and(|ext| {
+          options.extensions.iter().any(|wanted| ext.eq_ignore_ascii_case(wanted.as_str()))
+      })
+  })?;
+  
+  if files.is_empty() {
+      reporter.message(&format!("[remux] {} 当前目录下未发现 {} 文件", input_dir.display(), options.extensions.join("/")));
+      return Ok(Vec::new());
+  }
+  
+  // mp4 中的 AAC 与 ts 中的格式不同，ffmpeg 会自动插入 aac_adtstoasc 进行转换
+  let mut requirements = capabilities::format_requirements(&files, false);
+  if let Some((_, muxer)) = capabilities::formats_for_extension(&options.format) {
+      requirements.push(Requirement::Muxer(muxer));
+  }
+  let has_ts = files.iter().any(|file| file.extension().is_some_and(|ext| ext == "ts" || ext == "m3u8"));
+  if options.format == "mp4" && has_ts {
+      requirements.push(Requirement::Bsf("aac_adtstoasc"));
+  }
+  capabilities::require(&requirements)?;
+  
+  for file in &files {
+      emit_event(reporter, job, "remux", JobEvent::FileDiscovered { file: file.display().to_string() });
+  }
+  
+  let ledger = Ledger::open(output_dir)?;
+  let template = options.output_template().map_err(invalid_template)?;
+  
+  // m3u8 播放列表引用的分片会随播放列表一起转换
+  let segments: HashSet<PathBuf> = files.iter()
+      .filter(|file| file.extension().is_some_and(|ext| ext == "m3u8"))
+      .flat_map(|playlist| remux::playlist_segments(playlist))
+      .collect();
+  
+  // 逐个检查文件，筛选出需要转换的文件
+  let total = files.len();
+  let processed = AtomicUsize::new(0);
+  let report_progress = |file: &str| {
+      let current = processed.fetch_add(1, Ordering::SeqCst) + 1;
+      emit_event(reporter, job, "remux", JobEvent::Progress { file: file.to_string(), current, total });
+  };
+  
+  let mut pending_files = Vec::new();
+  let mut reserved = HashSet::new();
+  let mut plan = options.dry_run.then(Vec::new);
+  for file in files {
+      jobs::check_cancelled(job)?;
+      
+      let file_name = file.file_stem().ok_or_else(|| Error::InvalidPath(file.clone()))?
+          .to_string_lossy().to_string();
+      let file_display = file.display().to_string();
+      
+      if segments.contains(&file) {
+          if options.debug {
+              reporter.message(&format!("[remux] {} 是播放列表的分片，随播放列表一起转换", file_display));
+          }
+          report_progress(&file_display);
+          skip_file(reporter, job, "remux", &mut plan, file_display, SkipReason::PlaylistSegment);
+          stats.skipped += 1;
+          continue;
+      }
+      
+      if ledger.contains(&file) {
+          report_progress(&file_display);
+          skip_file(reporter, job, "remux", &mut plan, file_display, SkipReason::AlreadyProcessed);
+          stats.skipped += 1;
+          continue;
+      }
+      
+      // 上次转换失败的文件按退避时间重试，失败次数达到上限后不再处理
+      match ledger.retry_state(&file, &options.retry) {
+          RetryState::Ready => {},
+          RetryState::Waiting(remaining) => {
+              if options.debug {
+                  reporter.message(&format!("[remux] {} 上次处理失败，{} 秒后重试", file_name, remaining.as_secs()));
+              }
+              report_progress(&file_display);
+              skip_file(reporter, job, "remux", &mut plan, file_display, SkipReason::RetryLater);
+              stats.skipped += 1;
+              continue;
+          },
+          RetryState::Exhausted(attempts) => {
+              if options.debug {
+                  reporter.message(&format!("[remux] {} 已失败 {} 次，跳过", file_name, attempts));
+              }
+              report_progress(&file_display);
+              skip_file(reporter, job, "remux", &mut plan, file_display, SkipReason::TooManyFailures);
+              stats.skipped += 1;
+              continue;
+          }
+      }
+      
+      let dest_path = match template.render(&TemplateInput {
+          source: &file,
+          root: input_dir,
+          stem: &file_name,
+          ext: &options.format,
+      }) {
+          Ok(relative) => output_dir.join(relative),
+          Err(e) => {
+              reporter.message(&format!("[remux] {}", e));
+              report_progress(&file_display);
+              skip_file(reporter, job, "remux", &mut plan, file_display, SkipReason::InvalidDestination);
+              stats.skipped += 1;
+              continue;
+          }
+      };
+      
+      // 检查编码能否直接放入目标容器，ffprobe 不可用时直接尝试转换
+      // ffprobe 无法读取的文件与转换失败一样记入失败记录
+      let info = match probe::probe_media(&file) {
+          Ok(info) => Some(info),
+          Err(e) if !probe::is_available() => {
+              reporter.message(&format!("[remux] 无法检查 {} 的编码兼容性，直接尝试转换：{}", file_name, e));
+              None
+          },
+          Err(e) => {
+              reporter.message(&format!("[remux] {}", e));
+              report_progress(&file_display);
+              if plan.is_some() {
+                  skip_file(reporter, job, "remux", &mut plan, file_display, SkipReason::UnreadableMedia);
+                  stats.skipped += 1;
+              } else {
+                  emit_event(reporter, job, "remux", JobEvent::Failed { file: file_display, stderr: e.to_string() });
+                  let failed_dir = failed_dir(input_dir, output_dir, &file);
+                  record_failure(&options.retry, &ledger, &[&file], &failed_dir, &e.to_string(), "[remux]", reporter);
+                  stats.failed += 1;
+              }
+              continue;
+          }
+      };
+      let incompatible = match info {
+          Some(info) => {
+              let check = remux::check_codecs(&info.streams, &options.format);
+              if !check.unsupported.is_empty() {
+                  Some(format!("[remux] {}：{} 无法直接放入 {} 容器，请先转码或改用 mkv",
+                      file_name, check.unsupported.join("、"), options.format))
+              } else if !check.experimental.is_empty() && !options.strict {
+                  Some(format!("[remux] {}：{} 在 {} 容器中仅为实验性支持，需要添加 --strict 参数或先转码",
+                      file_name, check.experimental.join("、"), options.format))
+              } else {
+                  if !check.experimental.is_empty() {
+                      let msg = format!("[remux] {}：{} 将以实验性方式写入 {} 容器，部分播放器可能无法播放",
+                          file_name, check.experimental.join("、"), options.format);
+                      reporter.message(&msg);
+                  }
+                  None
+              }
+          },
+          None => None,
+      };
+      
+      if let Some(msg) = incompatible {
+          reporter.message(&msg);
+          report_progress(&file_display);
+          skip_file(reporter, job, "remux", &mut plan, file_display, SkipReason::IncompatibleCodec);
+          stats.skipped += 1;
+          continue;
+      }
+      
+      // 目标文件已存在时按冲突处理方式跳过、覆盖或改名
+      let dest_path = match resolve_dest(options.collision, &[&file], dest_path, &reserved, "remux", reporter, job) {
+          Ok(dest_path) => dest_path,
+          Err(reason) => {
+              report_progress(&file_display);
+              skip_file(reporter, job, "remux", &mut plan, file_display, reason);
+              stats.skipped += 1;
+              continue;
+          }
+      };
+      
+      reserved.insert(dest_path.clone());
+      pending_files.push((file, dest_path));
+  }
+  
+  // 预演模式只生成执行计划，不执行转换
+  if let Some(mut plan) = plan {
+      for (file, dest_path) in &pending_files {
+          let cmd = remux_command(
+              file, &output::temp_path(dest_path), &remux::input_args(file), &remux::output_args(options.strict)
+          );
+          plan.push(PlanEntry {
+              file: file.display().to_string(),
+              action: PlanAction::Process,
+              reason: None,
+              dest: Some(dest_path.display().to_string()),
+              argv: command_argv(&cmd),
+              remove_source: options.remove,
+          });
+      }
+      return Ok(finish_plan(plan, reporter, job, "remux"));
+  }
+  
+  // 按指定的并发数执行转换，默认逐个执行
+  let stats = Mutex::new(stats);
+  pool::run(pending_files, options.jobs, |(file, dest_path)| {
+      jobs::check_cancelled(job)?;
+      report_progress(&file.display().to_string());
+      
+      let converted = remux_one(options, &file, &dest_path, &ledger, reporter, job)?;
+      if let Ok(mut stats) = stats.lock() {
+          if converted {
+              stats.converted += 1;
+          } else {
+              stats.failed += 1;
+          }
+      }
+      Ok(())
+  })?;
+  Ok(Vec::new())
+}
+
+// 转换单个文件到目标容器，返回是否转换成功
+fn remux_one(
+  options: &RemuxOptions,
+  file: &Path,
+  dest_path: &Path,
+  ledger: &Ledger,
+  reporter: &dyn Reporter,
+  job: Option<&JobHandle>
+) -> Result<bool, Error> {
+  let file_name = file.file_stem().ok_or_else(|| Error::InvalidPath(file.to_path_buf()))?
+      .to_string_lossy().to_string();
+  let file_display = file.display().to_string();
+  
+  reporter.message(&format!("[remux] 正在转换：{}", file.display()));
+  emit_event(reporter, job, "remux", JobEvent::Started { file: file_display.clone() });
+  
+  if let Some(parent) = dest_path.parent() {
+      fs::create_dir_all(parent).map_err(|e| Error::io("创建目标目录", parent, e))?;
+  }
+  
+  let start_time = std::time::Instant::now();
+  
+  let mut on_progress = |progress: FfmpegProgress| {
+      emit_event(reporter, job, "remux", JobEvent::FileProgress { file: file_display.clone(), progress });
+  };
+  
+  match remux_file(file, dest_path, &remux::input_args(file), &remux::output_args(options.strict), job, &mut on_progress) {
+      Ok(_) => {
+          let duration = start_time.elapsed().as_secs_f32();
+          reporter.message(&format!("[remux] {} 转换成功，耗时：{:.2}s", file_name, duration));
+          emit_event(reporter, job, "remux", JobEvent::Succeeded {
+              file: file_display.clone(),
+              elapsed: duration as f64,
+              dest: dest_path.display().to_string(),
+          });
+          
+          record_processed(ledger, &[file], dest_path, "remux", reporter);
+          
+          // 如果需要删除源文件，先确认输出文件完整，播放列表连同其分片一起移除
+          if options.remove {
+              let mut sources = vec![file.to_path_buf()];
+              if file.extension().is_some_and(|ext| ext == "m3u8") {
+                  sources.extend(remux::playlist_segments(file));
+              }
+              let output_dir = Path::new(&options.output_dir);
+              let removed = remove_sources(
+                  &options.removal, &sources, dest_path, Path::new(&options.cwd),
+                  &options.removal.hold_dir(output_dir), "[remux]", reporter
+              );
+              if let Err(reason) = removed {
+                  emit_event(reporter, job, "remux", JobEvent::VerifyFailed { file: file_display, reason });
+              }
+          }
+          
+          Ok(true)
+      },
+      Err(e) => {
+          jobs::check_cancelled(job)?;
+          let e = e.to_string();
+          reporter.message(&format!("[remux] {}转换失败：\n{}", file_name, e));
+          emit_event(reporter, job, "remux", JobEvent::Failed { file: file_display, stderr: e.clone() });
+          
+          let failed_dir = failed_dir(Path::new(&options.cwd), Path::new(&options.output_dir), file);
+          record_failure(&options.retry, ledger, &[file], &failed_dir, &e, "[remux]", reporter);
+          
+          Ok(false)
+      }
+  }
+}
+
+// 监视模式：目录中出现新的 flv 文件或有待复查的文件时重新执行转换，直到任务被取消
+// 转换与失败的数量在各次执行之间累计，跳过的数量只保留最近一次执行的结果，
+// 否则已转换过的文件每次执行都会被重复计为跳过
+pub fn watch_flv_to_mp4(options: &FlvToMp4Options, reporter: &dyn Reporter, job: &JobHandle, stats: &mut FileStats) {
+  let interval = std::time::Duration::from_secs(options.timeout);
+  let (watcher, fallback) = DirWatcher::new(Path::new(&options.cwd), "flv", options.scan.recursive, options.poll, interval);
+  if let Some(reason) = fallback {
+      reporter.message(&format!("[flv-to-mp4] 无法监听目录变化，改为每 {} 秒轮询一次：{}", options.timeout, reason));
+  } else if options.debug {
+      let mode = if watcher.is_polling() { "定时轮询" } else { "文件系统通知" };
+      reporter.message(&format!("[flv-to-mp4] 监视模式：{}", mode));
+  }
+  
+  let mut stability = StabilityTracker::new(options.stable_checks);
+  let mut watch_count = 0;
+  // --no-skip 只对第一次执行有效，之后转换过的文件不再重复转换
+  let mut current = options.clone();
+  loop {
+      let mut tick = FileStats::default();
+      let result = handle_flv_to_mp4(&current, reporter, Some(job), &mut tick, Some(&mut stability));
+      stats.converted += tick.converted;
+      stats.failed += tick.failed;
+      stats.skipped = tick.skipped;
+      if let Err(e) = result {
+          if job.is_cancelled() {
+              break;
+          }
+          reporter.message(&format!("执行出错: {}", e));
+      }
+      
+      current.skip = true;
+      watch_count += 1;
+      // 输出目录可能被目录配置修改
+      let output_dir = dir_config::flv_to_mp4_options(&current).map_or_else(|_| options.output_dir.clone(), |merged| merged.output_dir);
+      reporter.message(&format!("[flv-to-mp4][Watching][{}]=>[{}] 已执行 {} 次", options.cwd, output_dir, watch_count));
+      emit_event(reporter, Some(job), "flv2mp4", JobEvent::WatchTick {
+          count: watch_count,
+          input_dir: options.cwd.clone(),
+          output_dir,
+      });
+      
+      // 等待目录变化，或到了复查尚未录制完成的文件的时间
+      if !watcher.wait(job, stability.has_pending()) {
+          break;
+      }
+  }
+}
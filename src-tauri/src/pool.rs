@@ -0,0 +1,46 @@
+// 固定大小的工作线程池，用于并发执行多个 ffmpeg 任务
+
+use crate::error::Error;
+use std::sync::Mutex;
+use std::thread;
+
+// 使用 workers 个线程依次取出 items 进行处理，workers 不大于 1 时在当前线程中按顺序处理
+// 任一项返回错误（包括任务被取消）后，其余线程不再领取新的项，已开始的项会继续执行完
+pub fn run<T, F>(items: Vec<T>, workers: usize, f: F) -> Result<(), Error>
+where
+    T: Send,
+    F: Fn(T) -> Result<(), Error> + Sync,
+{
+    if workers <= 1 || items.len() <= 1 {
+        return items.into_iter().try_for_each(f);
+    }
+
+    let workers = workers.min(items.len());
+    let queue = Mutex::new(items.into_iter());
+    let error: Mutex<Option<Error>> = Mutex::new(None);
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                if !error.lock().is_ok_and(|error| error.is_none()) {
+                    break;
+                }
+                let item = match queue.lock().ok().and_then(|mut queue| queue.next()) {
+                    Some(item) => item,
+                    None => break,
+                };
+                if let Err(e) = f(item) {
+                    if let Ok(mut error) = error.lock() {
+                        error.get_or_insert(e);
+                    }
+                    break;
+                }
+            });
+        }
+    });
+
+    match error.into_inner() {
+        Ok(Some(e)) => Err(e),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,61 @@
+// 命名的参数预设，例如 "NAS 归档"、"本地快速转换"，保存在设置文件中，桌面应用与命令行工具共用
+
+use crate::error::Error;
+use crate::options::{CommandParams, ValidationError};
+use crate::settings::Settings;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Preset {
+    pub name: String,
+    // 与界面执行命令时相同的参数，commandType 为 flv2mp4、avm 或 remux
+    pub params: CommandParams,
+    // 界面表单的状态，用于在界面中恢复，命令行工具不使用
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub form: Option<serde_json::Value>,
+}
+
+pub fn list() -> Result<Vec<Preset>, Error> {
+    Ok(Settings::load()?.presets)
+}
+
+pub fn find(name: &str) -> Result<Preset, Error> {
+    list()?
+        .into_iter()
+        .find(|preset| preset.name == name)
+        .ok_or_else(|| not_found(name))
+}
+
+// 保存前按执行时的规则检查参数，同名的预设会被替换，返回保存后的全部预设
+pub fn save(mut preset: Preset) -> Result<Vec<Preset>, Error> {
+    preset.name = preset.name.trim().to_string();
+    if preset.name.is_empty() {
+        return Err(Error::Validation(vec![ValidationError::new("name", "预设名称不能为空")]));
+    }
+    preset.params.clone().into_options()?;
+
+    let mut settings = Settings::load()?;
+    match settings.presets.iter_mut().find(|existing| existing.name == preset.name) {
+        Some(existing) => *existing = preset,
+        None => settings.presets.push(preset),
+    }
+    settings.save()?;
+    Ok(settings.presets)
+}
+
+// 返回删除后的全部预设
+pub fn delete(name: &str) -> Result<Vec<Preset>, Error> {
+    let mut settings = Settings::load()?;
+    let count = settings.presets.len();
+    settings.presets.retain(|preset| preset.name != name);
+    if settings.presets.len() == count {
+        return Err(not_found(name));
+    }
+    settings.save()?;
+    Ok(settings.presets)
+}
+
+fn not_found(name: &str) -> Error {
+    Error::NotFound { kind: "预设", name: name.to_string() }
+}
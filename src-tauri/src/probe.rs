@@ -0,0 +1,206 @@
+// 通过 ffprobe 读取媒体文件的容器与流信息
+
+use crate::error::Error;
+use crate::ffmpeg;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+// 媒体文件信息，时长单位为秒，码率单位为 bit/s
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaInfo {
+    pub path: String,
+    // 容器格式，如 "flv"、"mov,mp4,m4a,3gp,3g2,mj2"
+    pub container: String,
+    pub container_long_name: Option<String>,
+    pub duration: Option<f64>,
+    pub bit_rate: Option<u64>,
+    pub size: Option<u64>,
+    // 第一个视频流的分辨率
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub streams: Vec<StreamInfo>,
+}
+
+// 单个流的编码信息，音频与视频特有的字段在其它类型的流中为 None
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamInfo {
+    pub index: u32,
+    // "video"、"audio"、"subtitle"、"data" 等
+    pub codec_type: String,
+    pub codec_name: String,
+    pub profile: Option<String>,
+    pub duration: Option<f64>,
+    pub bit_rate: Option<u64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub frame_rate: Option<f64>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u32>,
+    pub channel_layout: Option<String>,
+    pub language: Option<String>,
+}
+
+impl MediaInfo {
+    pub fn has_video(&self) -> bool {
+        self.streams.iter().any(|stream| stream.codec_type == "video")
+    }
+
+    pub fn has_audio(&self) -> bool {
+        self.streams.iter().any(|stream| stream.codec_type == "audio")
+    }
+}
+
+// ffprobe -of json 的原始输出，数值字段多以字符串表示
+#[derive(Deserialize)]
+struct RawOutput {
+    #[serde(default)]
+    streams: Vec<RawStream>,
+    format: Option<RawFormat>,
+}
+
+#[derive(Deserialize)]
+struct RawFormat {
+    #[serde(default)]
+    format_name: String,
+    format_long_name: Option<String>,
+    duration: Option<String>,
+    bit_rate: Option<String>,
+    size: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RawStream {
+    #[serde(default)]
+    index: u32,
+    #[serde(default)]
+    codec_type: String,
+    #[serde(default)]
+    codec_name: String,
+    profile: Option<String>,
+    duration: Option<String>,
+    bit_rate: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    avg_frame_rate: Option<String>,
+    r_frame_rate: Option<String>,
+    sample_rate: Option<String>,
+    channels: Option<u32>,
+    channel_layout: Option<String>,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+}
+
+// 解析 "30000/1001" 形式的帧率，0/0 表示未知
+fn parse_rate(value: &str) -> Option<f64> {
+    let (num, den) = value.split_once('/')?;
+    let num: f64 = num.parse().ok()?;
+    let den: f64 = den.parse().ok()?;
+    (num > 0.0 && den > 0.0).then(|| num / den)
+}
+
+fn parse_number<T: std::str::FromStr>(value: Option<&String>) -> Option<T> {
+    value.and_then(|value| value.parse().ok())
+}
+
+impl From<RawStream> for StreamInfo {
+    fn from(raw: RawStream) -> Self {
+        let frame_rate = raw.avg_frame_rate.as_deref().and_then(parse_rate)
+            .or_else(|| raw.r_frame_rate.as_deref().and_then(parse_rate));
+        StreamInfo {
+            index: raw.index,
+            codec_type: raw.codec_type,
+            codec_name: raw.codec_name,
+            profile: raw.profile,
+            duration: parse_number(raw.duration.as_ref()),
+            bit_rate: parse_number(raw.bit_rate.as_ref()),
+            width: raw.width,
+            height: raw.height,
+            frame_rate,
+            sample_rate: parse_number(raw.sample_rate.as_ref()),
+            channels: raw.channels,
+            channel_layout: raw.channel_layout,
+            language: raw.tags.get("language").cloned(),
+        }
+    }
+}
+
+// 读取媒体文件信息，文件无法识别或 ffprobe 执行失败时返回错误
+pub fn probe_media(path: &Path) -> Result<MediaInfo, Error> {
+    let mut cmd = ffmpeg::ffprobe_command();
+    cmd.args(["-v", "error", "-show_format", "-show_streams", "-of", "json"]).arg(path);
+    let output = cmd.output().map_err(|e| Error::io("执行 ffprobe", cmd.get_program(), e))?;
+
+    if !output.status.success() {
+        let reason = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(Error::InvalidMedia { path: path.to_path_buf(), reason });
+    }
+
+    let raw: RawOutput = serde_json::from_slice(&output.stdout).map_err(|e| Error::InvalidMedia {
+        path: path.to_path_buf(),
+        reason: format!("解析 ffprobe 输出失败: {}", e),
+    })?;
+
+    let streams: Vec<StreamInfo> = raw.streams.into_iter().map(StreamInfo::from).collect();
+    let first_video = streams.iter().find(|stream| stream.codec_type == "video");
+    let (width, height) = first_video.map_or((None, None), |stream| (stream.width, stream.height));
+    let format = raw.format;
+
+    Ok(MediaInfo {
+        path: path.display().to_string(),
+        container: format.as_ref().map(|format| format.format_name.clone()).unwrap_or_default(),
+        container_long_name: format.as_ref().and_then(|format| format.format_long_name.clone()),
+        duration: parse_number(format.as_ref().and_then(|format| format.duration.as_ref())),
+        bit_rate: parse_number(format.as_ref().and_then(|format| format.bit_rate.as_ref())),
+        size: parse_number(format.as_ref().and_then(|format| format.size.as_ref())),
+        width,
+        height,
+        streams,
+    })
+}
+
+// 是否找到了 ffprobe，查找方式见 ffmpeg.rs
+// 不可用时各命令跳过流检查，按文件名照常处理
+pub fn is_available() -> bool {
+    ffmpeg::resolve().is_ok_and(|info| info.ffprobe.is_some())
+}
+
+// 单元测试共用的假 ffprobe：直接输出被检查文件的内容，文件内容即为 ffprobe 的 JSON 结果
+// ffmpeg 路径与配置目录是整个进程共用的，只安装一次，避免并行执行的测试互相覆盖
+#[cfg(all(test, unix))]
+pub(crate) mod test_support {
+    use crate::{ffmpeg, settings};
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::sync::Once;
+
+    pub fn install_fake_ffprobe() {
+        static INSTALL: Once = Once::new();
+        INSTALL.call_once(|| {
+            let dir = std::env::temp_dir().join(format!("ffmpeg-script-unit-{}", std::process::id()));
+            std::env::set_var(settings::CONFIG_DIR_ENV, dir.join("config"));
+            let bin = dir.join("bin");
+            fs::create_dir_all(&bin).unwrap();
+            for (name, script) in [
+                ("ffmpeg", "#!/bin/sh\necho \"ffmpeg version 6.1-fake\"\n"),
+                ("ffprobe", "#!/bin/sh\nfor last in \"$@\"; do :; done\ncat \"$last\"\n"),
+            ] {
+                fs::write(bin.join(name), script).unwrap();
+                fs::set_permissions(bin.join(name), fs::Permissions::from_mode(0o755)).unwrap();
+            }
+            ffmpeg::set_path(Some(bin.to_str().unwrap())).unwrap();
+        });
+    }
+
+    // 生成假 ffprobe 输出的 JSON，codecs 为 (流类型, 编码)
+    pub fn fake_media(codecs: &[(&str, &str)], duration: &str) -> String {
+        let streams: Vec<String> = codecs
+            .iter()
+            .enumerate()
+            .map(|(index, (kind, codec))| format!(r#"{{ "index": {}, "codec_type": "{}", "codec_name": "{}" }}"#, index, kind, codec))
+            .collect();
+        format!(r#"{{ "streams": [{}], "format": {{ "format_name": "mp4", "duration": "{}" }} }}"#, streams.join(","), duration)
+    }
+}
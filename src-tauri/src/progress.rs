@@ -0,0 +1,218 @@
+// 解析 ffmpeg 的 -progress 输出，计算单个文件的处理进度
+
+use serde::Serialize;
+
+// 单个文件的实时处理进度，时间相关字段单位均为秒
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FfmpegProgress {
+    pub out_time: f64,
+    pub duration: Option<f64>,
+    pub percent: Option<f64>,
+    pub fps: Option<f64>,
+    pub speed: Option<f64>,
+    pub eta: Option<f64>,
+}
+
+// 读取线程发送给等待线程的消息
+pub enum ProgressMessage {
+    // 从 stderr 中解析到的输入文件时长
+    Duration(f64),
+    // 一个完整的 -progress 输出块
+    Update { out_time: f64, fps: Option<f64>, speed: Option<f64> },
+}
+
+// 解析 HH:MM:SS.xx 格式的时间
+pub fn parse_clock(value: &str) -> Option<f64> {
+    let mut parts = value.trim().split(':');
+    let hours: f64 = parts.next()?.parse().ok()?;
+    let minutes: f64 = parts.next()?.parse().ok()?;
+    let seconds: f64 = parts.next()?.parse().ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(hours * 3600.0 + minutes * 60.0 + seconds)
+}
+
+// 解析 ffmpeg stderr 中的 "  Duration: 00:01:23.45, start: ..." 行
+pub fn parse_duration(line: &str) -> Option<f64> {
+    let rest = line.trim_start().strip_prefix("Duration:")?;
+    let value = rest.split(',').next()?;
+    parse_clock(value).filter(|duration| *duration > 0.0)
+}
+
+// 逐行读取 -progress 输出，每遇到 progress=continue/end 即得到一个完整的进度块
+#[derive(Default)]
+pub struct ProgressParser {
+    out_time: f64,
+    fps: Option<f64>,
+    speed: Option<f64>,
+}
+
+impl ProgressParser {
+    pub fn feed(&mut self, line: &str) -> Option<ProgressMessage> {
+        let (key, value) = line.trim().split_once('=')?;
+        let value = value.trim();
+        match key {
+            "out_time_us" | "out_time_ms" => {
+                // 旧版本 ffmpeg 的 out_time_ms 实际上也是微秒
+                if let Ok(us) = value.parse::<i64>() {
+                    self.out_time = us.max(0) as f64 / 1_000_000.0;
+                }
+            },
+            "out_time" => {
+                if let Some(seconds) = parse_clock(value) {
+                    self.out_time = seconds;
+                }
+            },
+            "fps" => self.fps = value.parse().ok(),
+            "speed" => self.speed = value.trim_end_matches('x').trim().parse().ok(),
+            "progress" => {
+                return Some(ProgressMessage::Update {
+                    out_time: self.out_time,
+                    fps: self.fps,
+                    speed: self.speed,
+                });
+            },
+            _ => {}
+        }
+        None
+    }
+}
+
+// 结合输入时长把 -progress 输出换算为百分比与剩余时间
+#[derive(Default)]
+pub struct ProgressTracker {
+    duration: Option<f64>,
+}
+
+impl ProgressTracker {
+    pub fn update(&mut self, message: ProgressMessage) -> Option<FfmpegProgress> {
+        match message {
+            ProgressMessage::Duration(duration) => {
+                // 多个输入时以最长的为准
+                if self.duration.is_none_or(|current| duration > current) {
+                    self.duration = Some(duration);
+                }
+                None
+            },
+            ProgressMessage::Update { out_time, fps, speed } => {
+                let percent = self
+                    .duration
+                    .map(|duration| (out_time / duration * 100.0).clamp(0.0, 100.0));
+                let eta = match (self.duration, speed) {
+                    (Some(duration), Some(speed)) if speed > 0.0 => {
+                        Some(((duration - out_time) / speed).max(0.0))
+                    },
+                    _ => None,
+                };
+                Some(FfmpegProgress {
+                    out_time,
+                    duration: self.duration,
+                    percent,
+                    fps,
+                    speed,
+                    eta,
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 进度块中的 (out_time, fps, speed)
+    type Block = (f64, Option<f64>, Option<f64>);
+
+    // 把若干行依次交给解析器，返回每个完整进度块
+    fn parse_blocks(lines: &[&str]) -> Vec<Block> {
+        let mut parser = ProgressParser::default();
+        lines
+            .iter()
+            .filter_map(|line| match parser.feed(line)? {
+                ProgressMessage::Update { out_time, fps, speed } => Some((out_time, fps, speed)),
+                ProgressMessage::Duration(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parses_clock_and_duration_lines() {
+        let cases: [(&str, Option<f64>); 6] = [
+            ("00:00:10.50", Some(10.5)),
+            ("01:02:03", Some(3723.0)),
+            (" 00:01:00.00 ", Some(60.0)),
+            ("10.5", None),
+            ("00:00:00:01", None),
+            ("N/A", None),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(parse_clock(value), expected, "{}", value);
+        }
+
+        let cases: [(&str, Option<f64>); 4] = [
+            ("  Duration: 00:01:23.45, start: 0.000000, bitrate: 1 kb/s", Some(83.45)),
+            ("  Duration: N/A, start: 0.000000, bitrate: N/A", None),
+            ("  Duration: 00:00:00.00, start: 0.000000", None),
+            ("  Stream #0:0: Video: h264", None),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(parse_duration(line), expected, "{}", line);
+        }
+    }
+
+    #[test]
+    fn parses_progress_blocks() {
+        let cases: [(&[&str], &[Block]); 6] = [
+            // 完整的进度块
+            (
+                &["fps=25.00", "out_time_us=2500000", "speed=1.5x", "progress=continue"],
+                &[(2.5, Some(25.0), Some(1.5))],
+            ),
+            // 没有 progress= 行的不完整块不产生进度
+            (&["fps=25.00", "out_time_us=2500000", "speed=1.5x"], &[]),
+            // 开始时的 N/A 不改变已有的值
+            (&["out_time_us=N/A", "speed=N/A", "progress=continue"], &[(0.0, None, None)]),
+            (
+                &["out_time_us=1000000", "progress=continue", "out_time_us=N/A", "progress=continue"],
+                &[(1.0, None, None), (1.0, None, None)],
+            ),
+            // progress=end 同样结束一个进度块，旧版本的 out_time_ms 按微秒处理
+            (&["out_time_ms=4000000", "speed= 2.0x", "progress=end"], &[(4.0, None, Some(2.0))]),
+            // 只有 out_time 时按时钟格式解析，无关的行与没有 = 的行被忽略
+            (&["frame=10", "out_time=00:00:03.000000", "garbage", "progress=end"], &[(3.0, None, None)]),
+        ];
+        for (lines, expected) in cases {
+            assert_eq!(parse_blocks(lines), expected, "{:?}", lines);
+        }
+    }
+
+    #[test]
+    fn computes_percent_and_eta_from_duration() {
+        let update = |out_time, speed| ProgressMessage::Update { out_time, fps: None, speed };
+        let mut tracker = ProgressTracker::default();
+
+        // 还不知道时长时没有百分比与剩余时间
+        let progress = tracker.update(update(5.0, Some(2.0))).unwrap();
+        assert_eq!((progress.percent, progress.eta), (None, None));
+
+        // 多个输入时以最长的时长为准
+        assert!(tracker.update(ProgressMessage::Duration(20.0)).is_none());
+        assert!(tracker.update(ProgressMessage::Duration(10.0)).is_none());
+        // (out_time, speed, percent, eta)
+        let cases = [
+            (5.0, Some(2.0), Some(25.0), Some(7.5)),
+            (5.0, None, Some(25.0), None),
+            (5.0, Some(0.0), Some(25.0), None),
+            // 输出时长超过输入时长时不超过 100%
+            (25.0, Some(1.0), Some(100.0), Some(0.0)),
+        ];
+        for (out_time, speed, percent, eta) in cases {
+            let progress = tracker.update(update(out_time, speed)).unwrap();
+            assert_eq!(progress.duration, Some(20.0));
+            assert_eq!((progress.percent, progress.eta), (percent, eta), "{} {:?}", out_time, speed);
+        }
+    }
+}
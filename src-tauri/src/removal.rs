@@ -0,0 +1,226 @@
+// 处理成功后移除源文件：先用 ffprobe 校验输出文件，确认完整后再删除、移到回收站或暂存目录
+// ffmpeg 正常退出并不代表输出文件完整，直接删除源文件可能导致录像丢失
+
+use crate::output;
+use crate::probe;
+use crate::scan;
+use chrono::{Local, NaiveDate};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// 暂存目录下按移入日期分目录，目录名的格式
+const HOLD_DATE_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RemoveMode {
+    // 直接删除
+    Delete,
+    // 移到系统回收站（默认）
+    Trash,
+    // 移到暂存目录，超过保留天数后再删除
+    Hold,
+}
+
+impl RemoveMode {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "delete" => Ok(RemoveMode::Delete),
+            "trash" => Ok(RemoveMode::Trash),
+            "hold" => Ok(RemoveMode::Hold),
+            _ => Err(format!("无效的源文件移除方式: {}（可选 delete/trash/hold）", value)),
+        }
+    }
+}
+
+// -r 移除源文件时的设置
+#[derive(Clone, Debug)]
+pub struct RemovePolicy {
+    pub mode: RemoveMode,
+    // 暂存目录，未指定时为输出目录下的 removed 目录
+    pub hold_dir: Option<String>,
+    // 暂存目录中的文件保留的天数，0 表示一直保留
+    pub retention_days: u32,
+    // 输出文件与源文件的时长允许相差的秒数
+    pub duration_tolerance: f64,
+}
+
+impl Default for RemovePolicy {
+    fn default() -> Self {
+        RemovePolicy {
+            mode: RemoveMode::Trash,
+            hold_dir: None,
+            retention_days: 7,
+            duration_tolerance: 2.0,
+        }
+    }
+}
+
+impl RemovePolicy {
+    pub fn hold_dir(&self, output_dir: &Path) -> PathBuf {
+        match &self.hold_dir {
+            Some(dir) => PathBuf::from(dir),
+            None => output_dir.join("removed"),
+        }
+    }
+}
+
+// 校验输出文件：大小不为 0，音视频流的数量与源文件相同，时长在允许的误差内
+// ffprobe 不可用或无法读取文件时同样视为校验失败，宁可保留源文件
+pub fn verify_output(source: &Path, dest: &Path, tolerance: f64) -> Result<(), String> {
+    let size = fs::metadata(dest).map_err(|e| format!("读取输出文件失败: {}", e))?.len();
+    if size == 0 {
+        return Err("输出文件大小为 0".to_string());
+    }
+
+    let source_info = probe::probe_media(source).map_err(|e| e.to_string())?;
+    let dest_info = probe::probe_media(dest).map_err(|e| e.to_string())?;
+
+    let count = |streams: &[probe::StreamInfo]| {
+        streams
+            .iter()
+            .filter(|stream| stream.codec_type == "video" || stream.codec_type == "audio")
+            .count()
+    };
+    let (source_streams, dest_streams) = (count(&source_info.streams), count(&dest_info.streams));
+    if source_streams != dest_streams {
+        return Err(format!("输出文件有 {} 个音视频流，源文件有 {} 个", dest_streams, source_streams));
+    }
+
+    match (source_info.duration, dest_info.duration) {
+        (Some(expected), Some(actual)) if (expected - actual).abs() > tolerance => Err(format!(
+            "输出文件时长 {:.2}s 与源文件时长 {:.2}s 相差超过 {}s",
+            actual, expected, tolerance
+        )),
+        (Some(_), Some(_)) => Ok(()),
+        (_, None) => Err("无法读取输出文件的时长".to_string()),
+        (None, Some(_)) => Err("无法读取源文件的时长".to_string()),
+    }
+}
+
+// 按设置移除源文件，返回移动后的位置（直接删除或移到回收站时为 None）
+// 移到暂存目录时按当天日期分目录，并保留源文件所在的子目录结构
+pub fn remove_source(source: &Path, mode: RemoveMode, input_dir: &Path, hold_dir: &Path) -> Result<Option<PathBuf>, String> {
+    match mode {
+        RemoveMode::Delete => fs::remove_file(source)
+            .map(|_| None)
+            .map_err(|e| format!("删除源文件失败: {}", e)),
+        RemoveMode::Trash => trash::delete(source)
+            .map(|_| None)
+            .map_err(|e| format!("移到回收站失败: {}", e)),
+        RemoveMode::Hold => {
+            let dir = hold_dir
+                .join(Local::now().format(HOLD_DATE_FORMAT).to_string())
+                .join(scan::relative_parent(input_dir, source));
+            fs::create_dir_all(&dir).map_err(|e| format!("创建暂存目录失败: {}", e))?;
+            let dest = dir.join(source.file_name().unwrap_or_default());
+            output::move_file(source, &dest).map_err(|e| format!("移到暂存目录失败: {}", e))?;
+            Ok(Some(dest))
+        },
+    }
+}
+
+// 删除暂存目录中超过保留天数的日期目录，返回删除的目录数
+pub fn purge_expired(hold_dir: &Path, retention_days: u32) -> Result<usize, String> {
+    if retention_days == 0 || !hold_dir.is_dir() {
+        return Ok(0);
+    }
+    let today = Local::now().date_naive();
+    let mut removed = 0;
+    let entries = fs::read_dir(hold_dir).map_err(|e| format!("读取暂存目录失败: {}", e))?;
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        // 只处理按日期命名的目录，不会误删用户放入的其它文件
+        let date = match NaiveDate::parse_from_str(&name, HOLD_DATE_FORMAT) {
+            Ok(date) if entry.path().is_dir() => date,
+            _ => continue,
+        };
+        if (today - date).num_days() > i64::from(retention_days) {
+            fs::remove_dir_all(entry.path())
+                .map_err(|e| format!("清理暂存目录 {} 失败: {}", entry.path().display(), e))?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[cfg(unix)]
+    #[test]
+    fn verifies_output_before_removal() {
+        use crate::probe::test_support::{fake_media as media, install_fake_ffprobe};
+
+        install_fake_ffprobe();
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("a.flv");
+        fs::write(&source, media(&[("video", "h264"), ("audio", "aac"), ("data", "amf")], "10.0")).unwrap();
+        let dest = dir.path().join("a.mp4");
+
+        // (输出文件的内容, 校验失败时错误信息中包含的文字)，数据流不计入音视频流的数量
+        let cases = [
+            (media(&[("video", "h264"), ("audio", "aac")], "11.5"), None),
+            (media(&[("audio", "aac"), ("video", "h264")], "8.5"), None),
+            (String::new(), Some("大小为 0")),
+            (media(&[("video", "h264")], "10.0"), Some("有 1 个音视频流")),
+            (media(&[("video", "h264"), ("audio", "aac")], "12.5"), Some("相差超过 2s")),
+            (media(&[("video", "h264"), ("audio", "aac")], "N/A"), Some("无法读取输出文件的时长")),
+            ("not json".to_string(), Some("解析 ffprobe 输出失败")),
+        ];
+        for (content, expected) in cases {
+            fs::write(&dest, &content).unwrap();
+            match (verify_output(&source, &dest, 2.0), expected) {
+                (Ok(()), None) => {},
+                (Err(e), Some(expected)) => assert!(e.contains(expected), "{}: {}", content, e),
+                (result, _) => panic!("{}: {:?}", content, result),
+            }
+        }
+        assert!(verify_output(&source, &dir.path().join("missing.mp4"), 2.0).is_err());
+    }
+
+    #[test]
+    fn holds_sources_by_date_and_keeps_subdirectories() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("rec");
+        let source = input.join("2024/a.flv");
+        fs::create_dir_all(source.parent().unwrap()).unwrap();
+        fs::write(&source, "a").unwrap();
+        let hold_dir = dir.path().join("removed");
+
+        let moved_to = remove_source(&source, RemoveMode::Hold, &input, &hold_dir).unwrap().unwrap();
+
+        let today = Local::now().format(HOLD_DATE_FORMAT).to_string();
+        assert_eq!(moved_to, hold_dir.join(today).join("2024/a.flv"));
+        assert_eq!(fs::read_to_string(&moved_to).unwrap(), "a");
+        assert!(!source.exists());
+
+        let deleted = input.join("b.flv");
+        fs::write(&deleted, "b").unwrap();
+        assert_eq!(remove_source(&deleted, RemoveMode::Delete, &input, &hold_dir).unwrap(), None);
+        assert!(!deleted.exists());
+    }
+
+    #[test]
+    fn purges_only_expired_date_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let today = Local::now().date_naive();
+        let day = |days: i64| (today - Duration::days(days)).format(HOLD_DATE_FORMAT).to_string();
+        // 只有按日期命名的目录会被删除，同名的文件与其它目录保留
+        for name in [day(0), day(7), day(8), day(30), "notes".to_string()] {
+            fs::create_dir_all(dir.path().join(&name)).unwrap();
+            fs::write(dir.path().join(&name).join("a.flv"), "a").unwrap();
+        }
+        fs::write(dir.path().join(day(9)), "not a directory").unwrap();
+
+        // 保留天数为 0 时一直保留，暂存目录不存在时不做处理
+        assert_eq!(purge_expired(dir.path(), 0).unwrap(), 0);
+        assert_eq!(purge_expired(&dir.path().join("missing"), 7).unwrap(), 0);
+
+        assert_eq!(purge_expired(dir.path(), 7).unwrap(), 2);
+        for (name, kept) in [(day(0), true), (day(7), true), (day(8), false), (day(30), false), (day(9), true), ("notes".to_string(), true)] {
+            assert_eq!(dir.path().join(&name).exists(), kept, "{}", name);
+        }
+    }
+}
@@ -0,0 +1,106 @@
+// 通用的容器转换（remux）：不重新编码，只把音视频流复制到新的容器中
+// 转换前通过 ffprobe 检查编码是否能直接放入目标容器
+
+use crate::probe::StreamInfo;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// 支持的目标容器
+pub const TARGET_FORMATS: &[&str] = &["mp4", "mkv"];
+
+// 未指定源文件扩展名时默认处理的格式（会排除与目标容器相同的扩展名）
+pub const SOURCE_EXTENSIONS: &[&str] = &["flv", "ts", "mkv", "webm", "m3u8", "mp4"];
+
+// 编码在目标容器中的支持程度
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Support {
+    Supported,
+    // ffmpeg 需要 -strict experimental 才能写入
+    Experimental,
+    Unsupported,
+}
+
+fn codec_support(format: &str, stream: &StreamInfo) -> Support {
+    let codec = stream.codec_name.as_str();
+    match (format, stream.codec_type.as_str()) {
+        // mkv 几乎可以容纳任何音视频编码
+        ("mkv", _) => Support::Supported,
+        ("mp4", "video") => match codec {
+            "h264" | "hevc" | "av1" | "mpeg4" | "mpeg2video" | "mpeg1video" | "mjpeg" | "png" => {
+                Support::Supported
+            },
+            "vp9" => Support::Experimental,
+            _ => Support::Unsupported,
+        },
+        ("mp4", "audio") => match codec {
+            "aac" | "mp3" | "mp2" | "ac3" | "eac3" | "alac" => Support::Supported,
+            "opus" | "flac" | "truehd" => Support::Experimental,
+            _ if codec.starts_with("pcm_") => Support::Experimental,
+            _ => Support::Unsupported,
+        },
+        _ => Support::Supported,
+    }
+}
+
+// 编码兼容性检查结果，列表中为 "视频编码 vp9" 形式的说明
+#[derive(Debug, Default)]
+pub struct CodecCheck {
+    pub experimental: Vec<String>,
+    pub unsupported: Vec<String>,
+}
+
+// 检查会被复制到目标容器的音视频流，字幕与数据流不会保留
+pub fn check_codecs(streams: &[StreamInfo], format: &str) -> CodecCheck {
+    let mut check = CodecCheck::default();
+    for stream in streams {
+        let kind = match stream.codec_type.as_str() {
+            "video" => "视频编码",
+            "audio" => "音频编码",
+            _ => continue,
+        };
+        let description = format!("{} {}", kind, stream.codec_name);
+        match codec_support(format, stream) {
+            Support::Supported => {},
+            Support::Experimental => check.experimental.push(description),
+            Support::Unsupported => check.unsupported.push(description),
+        }
+    }
+    check
+}
+
+// -i 之前的参数，m3u8 播放列表需要允许读取其中引用的本地或网络分片
+pub fn input_args(input: &Path) -> Vec<String> {
+    if input.extension().is_some_and(|ext| ext == "m3u8") {
+        vec!["-protocol_whitelist".into(), "file,http,https,tcp,tls,crypto".into()]
+    } else {
+        Vec::new()
+    }
+}
+
+// -i 之后的参数：复制所有音视频流，strict 时允许写入实验性支持的编码
+pub fn output_args(strict: bool) -> Vec<String> {
+    let mut args: Vec<String> = ["-map", "0:v?", "-map", "0:a?", "-c", "copy"]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+    if strict {
+        args.extend(["-strict".to_string(), "experimental".to_string()]);
+    }
+    args
+}
+
+// 读取 m3u8 播放列表中引用的本地分片文件，这些分片随播放列表一起转换，不再单独处理
+pub fn playlist_segments(playlist: &Path) -> HashSet<PathBuf> {
+    let dir = playlist.parent().unwrap_or(Path::new(""));
+    fs::read_to_string(playlist)
+        .map(|content| {
+            content
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.contains("://"))
+                .map(|line| dir.join(line))
+                .collect()
+        })
+        .unwrap_or_default()
+}
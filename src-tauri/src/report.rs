@@ -0,0 +1,91 @@
+// 处理流程的输出：文本提示与结构化的文件事件
+// 桌面应用发送到窗口，命令行工具输出到终端，测试中收集到内存里再检查
+
+use crate::events::{JobEvent, JobEventPayload};
+use crate::pipeline;
+use std::io::{IsTerminal, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+// 并发处理时多个线程共用同一个 Reporter，因此要求 Sync
+pub trait Reporter: Sync {
+    // 文本提示，与旧版脚本中 console.log 输出的内容相同
+    fn message(&self, msg: &str);
+    // 结构化的文件事件，前端据此展示每个文件的状态
+    fn event(&self, payload: JobEventPayload);
+}
+
+// 输出到终端：提示信息输出到 stdout，progress 为 true 时 ffmpeg 的实时进度显示在 stderr 的同一行
+#[derive(Default)]
+pub struct StdoutReporter {
+    pub progress: bool,
+}
+
+impl StdoutReporter {
+    // stderr 是终端时才显示实时进度，重定向到文件时不输出控制字符
+    pub fn terminal() -> Self {
+        StdoutReporter { progress: std::io::stderr().is_terminal() }
+    }
+}
+
+impl Reporter for StdoutReporter {
+    fn message(&self, msg: &str) {
+        println!("{}", msg);
+    }
+
+    fn event(&self, payload: JobEventPayload) {
+        if !self.progress {
+            return;
+        }
+        match payload.event {
+            JobEvent::FileProgress { file, progress } => {
+                let name = Path::new(&file).file_name().unwrap_or_default().to_string_lossy().to_string();
+                let mut line = format!("{} {}", pipeline::tips_head(&payload.command_type), name);
+                match progress.percent {
+                    Some(percent) => line.push_str(&format!(" {:.1}%", percent)),
+                    None => line.push_str(&format!(" {:.0}s", progress.out_time)),
+                }
+                if let Some(speed) = progress.speed {
+                    line.push_str(&format!(" {:.1}x", speed));
+                }
+                if let Some(eta) = progress.eta {
+                    line.push_str(&format!(" 剩余 {:.0}s", eta));
+                }
+                eprint!("\r\x1b[2K{}", line);
+            },
+            // 文件处理结束后清除进度行
+            JobEvent::Succeeded { .. } | JobEvent::Failed { .. } | JobEvent::VerifyFailed { .. } => {
+                eprint!("\r\x1b[2K");
+            },
+            _ => return,
+        }
+        let _ = std::io::stderr().flush();
+    }
+}
+
+// 收集到内存中，用于测试
+#[derive(Default)]
+pub struct MemoryReporter {
+    messages: Mutex<Vec<String>>,
+    events: Mutex<Vec<JobEventPayload>>,
+}
+
+impl MemoryReporter {
+    pub fn messages(&self) -> Vec<String> {
+        self.messages.lock().unwrap().clone()
+    }
+
+    pub fn events(&self) -> Vec<JobEvent> {
+        self.events.lock().unwrap().iter().map(|payload| payload.event.clone()).collect()
+    }
+}
+
+impl Reporter for MemoryReporter {
+    fn message(&self, msg: &str) {
+        self.messages.lock().unwrap().push(msg.to_string());
+    }
+
+    fn event(&self, payload: JobEventPayload) {
+        self.events.lock().unwrap().push(payload);
+    }
+}
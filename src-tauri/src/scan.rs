@@ -0,0 +1,132 @@
+// 扫描工作目录中的待处理文件，支持递归、深度限制、include/exclude 规则与符号链接策略
+
+use crate::error::Error;
+use crate::options::ValidationError;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::io;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+// 符号链接的处理方式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    // 忽略所有符号链接
+    Skip,
+    // 处理指向文件的符号链接，但不进入指向目录的符号链接（默认）
+    Files,
+    // 文件与目录的符号链接都跟随
+    Follow,
+}
+
+impl SymlinkPolicy {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "skip" => Ok(SymlinkPolicy::Skip),
+            "files" => Ok(SymlinkPolicy::Files),
+            "follow" => Ok(SymlinkPolicy::Follow),
+            _ => Err(format!("无效的符号链接策略: {}（可选 skip/files/follow）", value)),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ScanOptions {
+    pub recursive: bool,
+    // 递归时的最大深度，1 表示只扫描顶层目录，None 表示不限制
+    pub max_depth: Option<usize>,
+    // 相对于工作目录的路径匹配规则，include 为空时不限制
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub symlinks: SymlinkPolicy,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        ScanOptions {
+            recursive: false,
+            max_depth: None,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            symlinks: SymlinkPolicy::Files,
+        }
+    }
+}
+
+// field 为规则对应的参数名，规则无效时作为参数错误返回
+fn build_globset(field: &str, patterns: &[String]) -> Result<GlobSet, Error> {
+    let invalid = |message: String| Error::Validation(vec![ValidationError::new(field, message)]);
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern).map_err(|e| invalid(format!("无效的匹配规则 {}: {}", pattern, e)))?;
+        builder.add(glob);
+    }
+    builder.build().map_err(|e| invalid(format!("构建匹配规则失败: {}", e)))
+}
+
+// 扫描 root 下满足 accept 条件的文件，结果按路径排序
+// skip_dirs 通常为位于工作目录内的输出目录与暂存目录，避免把处理结果或已移除的源文件再次当作输入
+pub fn scan_files(
+    root: &Path,
+    options: &ScanOptions,
+    skip_dirs: &[&Path],
+    accept: impl Fn(&Path) -> bool,
+) -> Result<Vec<PathBuf>, Error> {
+    let include = build_globset("include", &options.include)?;
+    let exclude = build_globset("exclude", &options.exclude)?;
+    let max_depth = if options.recursive {
+        options.max_depth.unwrap_or(usize::MAX).max(1)
+    } else {
+        1
+    };
+
+    let walker = WalkDir::new(root)
+        .min_depth(1)
+        .max_depth(max_depth)
+        .follow_links(options.symlinks == SymlinkPolicy::Follow)
+        .into_iter()
+        .filter_entry(|entry| {
+            let relative = entry.path().strip_prefix(root).unwrap_or(entry.path());
+            if exclude.is_match(relative) {
+                return false;
+            }
+            !(entry.file_type().is_dir() && skip_dirs.iter().any(|dir| entry.path() == *dir))
+        });
+
+    let mut files = Vec::new();
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            // 工作目录本身无法读取时报错，子目录的错误（无权限、符号链接循环等）直接跳过
+            Err(e) if e.depth() == 0 => return Err(Error::io("读取目录", root, io::Error::from(e))),
+            Err(_) => continue,
+        };
+        let path = entry.path();
+
+        if entry.path_is_symlink() && options.symlinks == SymlinkPolicy::Skip {
+            continue;
+        }
+        // 未跟随的符号链接需要再确认一次指向的是否为文件
+        if !path.is_file() {
+            continue;
+        }
+
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        if !include.is_empty() && !include.is_match(relative) {
+            continue;
+        }
+        if accept(path) {
+            files.push(path.to_path_buf());
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+// 文件相对于工作目录的父目录，用于在输出目录中保留相同的子目录结构
+pub fn relative_parent(root: &Path, file: &Path) -> PathBuf {
+    file.parent()
+        .and_then(|parent| parent.strip_prefix(root).ok())
+        .map(Path::to_path_buf)
+        .unwrap_or_default()
+}
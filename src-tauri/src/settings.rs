@@ -0,0 +1,125 @@
+// 应用设置，保存在应用配置目录下的 settings.json，桌面应用与命令行工具共用
+
+use crate::error::Error;
+use crate::presets::Preset;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+// 与 tauri.conf.json 中的 identifier 相同，和 Tauri 的 app_config_dir 是同一个目录
+const APP_IDENTIFIER: &str = "com.ffmpeg.script";
+
+// 指定配置目录的环境变量，便于携带配置或在测试中隔离
+pub const CONFIG_DIR_ENV: &str = "FFMPEG_SCRIPT_CONFIG_DIR";
+
+const SETTINGS_FILE: &str = "settings.json";
+
+// 修改设置的结构时增加版本号，并在 MIGRATIONS 中添加从上一版本升级的迁移
+const SETTINGS_VERSION: u32 = 2;
+
+// MIGRATIONS[n] 把版本 n + 1 的设置升级到版本 n + 2
+const MIGRATIONS: [fn(&mut Map<String, Value>); (SETTINGS_VERSION - 1) as usize] = [migrate_v1_to_v2];
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    pub version: u32,
+    // 用户指定的 ffmpeg 路径，可以是可执行文件或其所在的目录
+    pub ffmpeg_path: Option<String>,
+    // 命名的参数预设，版本 2 新增
+    pub presets: Vec<Preset>,
+    // 无法读取的预设（例如被手动改坏或由更新的应用写入），原样保留并在保存时写回
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub invalid_presets: Vec<Value>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            version: SETTINGS_VERSION,
+            ffmpeg_path: None,
+            presets: Vec::new(),
+            invalid_presets: Vec::new(),
+        }
+    }
+}
+
+pub fn config_dir() -> Result<PathBuf, Error> {
+    if let Some(dir) = std::env::var_os(CONFIG_DIR_ENV) {
+        return Ok(PathBuf::from(dir));
+    }
+    dirs::config_dir()
+        .map(|dir| dir.join(APP_IDENTIFIER))
+        .ok_or(Error::ConfigDirNotFound)
+}
+
+impl Settings {
+    // 设置文件不存在时使用默认设置，旧版本的设置升级到当前版本，下次保存时写回
+    // 内容无法解析或版本高于当前应用时返回 InvalidData，与读取失败区分
+    pub fn load() -> Result<Self, Error> {
+        let path = config_dir()?.join(SETTINGS_FILE);
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Settings::default()),
+            Err(e) => return Err(Error::io("读取设置", &path, e)),
+        };
+        let value: Value = serde_json::from_str(&content).map_err(|e| Error::invalid_data(&path, e.to_string()))?;
+        let mut value = migrate(value).map_err(|message| Error::invalid_data(&path, message))?;
+        set_aside_invalid_presets(&mut value);
+        serde_json::from_value(value).map_err(|e| Error::invalid_data(&path, e.to_string()))
+    }
+
+    // 先写入临时文件再重命名，避免写到一半时中断导致设置损坏
+    pub fn save(&self) -> Result<(), Error> {
+        let dir = config_dir()?;
+        fs::create_dir_all(&dir).map_err(|e| Error::io("创建配置目录", &dir, e))?;
+        let path = dir.join(SETTINGS_FILE);
+        let content = serde_json::to_string_pretty(self).map_err(|e| Error::invalid_data(&path, e.to_string()))?;
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, content).map_err(|e| Error::io("保存设置", &tmp_path, e))?;
+        fs::rename(&tmp_path, &path).map_err(|e| Error::io("保存设置", &path, e))
+    }
+}
+
+// 依次执行迁移，把设置升级到当前版本，没有版本号的设置视为版本 1
+fn migrate(value: Value) -> Result<Value, String> {
+    let Value::Object(mut settings) = value else {
+        return Err("设置文件格式错误".into());
+    };
+    let version = settings.get("version").and_then(Value::as_u64).unwrap_or(1) as u32;
+    if version > SETTINGS_VERSION {
+        return Err(format!("设置文件的版本 {} 高于当前应用支持的版本 {}，请升级应用", version, SETTINGS_VERSION));
+    }
+    for migration in &MIGRATIONS[version.max(1) as usize - 1..] {
+        migration(&mut settings);
+    }
+    settings.insert("version".into(), SETTINGS_VERSION.into());
+    Ok(Value::Object(settings))
+}
+
+// 版本 2 增加了预设列表
+fn migrate_v1_to_v2(settings: &mut Map<String, Value>) {
+    settings.entry("presets").or_insert_with(|| Value::Array(Vec::new()));
+}
+
+// 把无法读取的预设移到 invalidPresets 中，一个预设有误时其余设置与预设照常读取
+fn set_aside_invalid_presets(settings: &mut Value) {
+    let Some(Value::Array(presets)) = settings.get_mut("presets") else {
+        return;
+    };
+    let (valid, invalid): (Vec<Value>, Vec<Value>) = presets
+        .drain(..)
+        .partition(|preset| Preset::deserialize(preset).is_ok());
+    *presets = valid;
+    if invalid.is_empty() {
+        return;
+    }
+    if let Some(Value::Array(list)) = settings
+        .as_object_mut()
+        .map(|settings| settings.entry("invalidPresets").or_insert_with(|| Value::Array(Vec::new())))
+    {
+        list.extend(invalid);
+    }
+}
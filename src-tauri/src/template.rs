@@ -0,0 +1,349 @@
+// 输出路径模板，例如 "{capture:room}/{date:%Y/%m}/{stem}.mp4"
+// 支持的占位符：
+//   {date:格式}     源文件的日期，格式同 chrono 的 strftime，可包含 "/" 生成多级目录
+//   {stem}          输出文件的基础文件名，{ext} 为输出文件的扩展名
+//   {parent}        源文件所在目录的名称
+//   {dir}           源文件相对于工作目录的子目录，用于在输出目录中保留原有的目录结构
+//   {capture:名称}  --name-pattern 正则表达式在文件名上匹配到的分组，名称也可以是分组序号
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local, NaiveDate, TimeZone};
+use regex::Regex;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+
+// {date} 使用的日期来源
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DateSource {
+    // 文件修改时间（默认）
+    Mtime,
+    // 文件创建时间，文件系统不支持时使用修改时间
+    Ctime,
+    // 从文件名中解析的时间，如 "xxx-2024-03-08-21-30-15.flv"，解析失败时使用修改时间
+    Name,
+}
+
+impl DateSource {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "mtime" => Ok(DateSource::Mtime),
+            "ctime" => Ok(DateSource::Ctime),
+            "name" => Ok(DateSource::Name),
+            _ => Err(format!("无效的日期来源: {}（可选 mtime/ctime/name）", value)),
+        }
+    }
+}
+
+// 各命令共用的模板参数
+#[derive(Clone, Debug)]
+pub struct TemplateOptions {
+    // 为 None 时使用各命令的默认布局
+    pub template: Option<String>,
+    pub date_source: DateSource,
+    pub name_pattern: Option<String>,
+}
+
+impl Default for TemplateOptions {
+    fn default() -> Self {
+        TemplateOptions {
+            template: None,
+            date_source: DateSource::Mtime,
+            name_pattern: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Segment {
+    Text(String),
+    Date(String),
+    Stem,
+    Ext,
+    Parent,
+    Dir,
+    Capture(String),
+}
+
+// 渲染单个文件的输出路径所需的信息
+pub struct TemplateInput<'a> {
+    // 用于读取日期、父目录与匹配文件名的源文件
+    pub source: &'a Path,
+    // 工作目录，{dir} 为源文件相对于它的子目录
+    pub root: &'a Path,
+    pub stem: &'a str,
+    pub ext: &'a str,
+}
+
+pub struct PathTemplate {
+    segments: Vec<Segment>,
+    date_source: DateSource,
+    name_pattern: Option<Regex>,
+}
+
+impl PathTemplate {
+    // 解析模板，占位符无效或正则表达式有误时返回错误
+    pub fn new(template: &str, options: &TemplateOptions) -> Result<Self, String> {
+        let name_pattern = match &options.name_pattern {
+            Some(pattern) => Some(
+                Regex::new(pattern).map_err(|e| format!("无效的文件名正则表达式 {}: {}", pattern, e))?,
+            ),
+            None => None,
+        };
+
+        let mut segments = Vec::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                segments.push(Segment::Text(rest[..start].to_string()));
+            }
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| format!("模板中的占位符缺少 }}: {}", template))?;
+            let placeholder = &rest[start + 1..start + end];
+            let (name, arg) = match placeholder.split_once(':') {
+                Some((name, arg)) => (name, Some(arg)),
+                None => (placeholder, None),
+            };
+            let segment = match (name, arg) {
+                ("date", Some(format)) => {
+                    // 无效的格式在渲染时会导致 panic，需要提前检查
+                    if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+                        return Err(format!("模板中的日期格式无效: {}", format));
+                    }
+                    Segment::Date(format.to_string())
+                },
+                ("stem", None) => Segment::Stem,
+                ("ext", None) => Segment::Ext,
+                ("parent", None) => Segment::Parent,
+                ("dir", None) => Segment::Dir,
+                ("capture", Some(group)) => {
+                    let known = name_pattern.as_ref().is_some_and(|regex| {
+                        regex.capture_names().flatten().any(|name| name == group)
+                            || group.parse::<usize>().is_ok_and(|index| index < regex.captures_len())
+                    });
+                    if !known {
+                        return Err(format!("模板中的 {{capture:{}}} 在 --name-pattern 中没有对应的分组", group));
+                    }
+                    Segment::Capture(group.to_string())
+                },
+                _ => return Err(format!("模板中有无法识别的占位符: {{{}}}", placeholder)),
+            };
+            segments.push(segment);
+            rest = &rest[start + end + 1..];
+        }
+        if !rest.is_empty() {
+            segments.push(Segment::Text(rest.to_string()));
+        }
+
+        Ok(PathTemplate {
+            segments,
+            date_source: options.date_source,
+            name_pattern,
+        })
+    }
+
+    // 生成相对于输出目录的路径，结果不能是绝对路径或包含 ".."
+    pub fn render(&self, input: &TemplateInput) -> Result<PathBuf, String> {
+        let file_name = input.source.file_name().unwrap_or_default().to_string_lossy();
+        let captures = self
+            .name_pattern
+            .as_ref()
+            .map(|regex| regex.captures(&file_name));
+        let mut date = None;
+
+        let mut rendered = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => rendered.push_str(text),
+                Segment::Date(format) => {
+                    let date = date.get_or_insert_with(|| source_date(input.source, self.date_source));
+                    rendered.push_str(&date.format(format).to_string());
+                },
+                Segment::Stem => rendered.push_str(&sanitize(input.stem)),
+                Segment::Ext => rendered.push_str(input.ext),
+                Segment::Parent => {
+                    let parent = input.source.parent().and_then(Path::file_name).unwrap_or_default();
+                    rendered.push_str(&sanitize(&parent.to_string_lossy()));
+                },
+                Segment::Dir => {
+                    let dir = input.source.parent()
+                        .and_then(|parent| parent.strip_prefix(input.root).ok())
+                        .unwrap_or(Path::new(""));
+                    // 位于工作目录下的文件 {dir} 为空，用 "." 占位，下面会与其它 "." 一起去掉
+                    if dir.as_os_str().is_empty() {
+                        rendered.push('.');
+                    } else {
+                        rendered.push_str(&dir.to_string_lossy());
+                    }
+                },
+                Segment::Capture(group) => {
+                    let captures = captures.as_ref().and_then(Option::as_ref).ok_or_else(|| {
+                        format!("文件名 {} 与 --name-pattern 不匹配", file_name)
+                    })?;
+                    let value = match group.parse::<usize>() {
+                        Ok(index) => captures.get(index),
+                        Err(_) => captures.name(group),
+                    };
+                    rendered.push_str(&sanitize(value.map(|m| m.as_str()).unwrap_or_default()));
+                },
+            }
+        }
+
+        // 去掉空的目录层级，例如 {dir} 为空时的 "./xxx.mp4"
+        let path: PathBuf = Path::new(&rendered)
+            .components()
+            .filter(|component| !matches!(component, Component::CurDir))
+            .collect();
+        let valid = path.file_name().is_some()
+            && path.components().all(|component| matches!(component, Component::Normal(_)));
+        if !valid {
+            return Err(format!("模板生成的输出路径无效: {}", rendered));
+        }
+        Ok(path)
+    }
+}
+
+// 文件名、目录名与正则分组中的路径分隔符会产生意外的目录层级，替换为下划线
+fn sanitize(value: &str) -> String {
+    value.replace(['/', '\\'], "_")
+}
+
+fn source_date(source: &Path, date_source: DateSource) -> DateTime<Local> {
+    let metadata = fs::metadata(source).ok();
+    let modified = metadata.as_ref().and_then(|metadata| metadata.modified().ok());
+    let created = metadata.as_ref().and_then(|metadata| metadata.created().ok());
+    let from_name = || {
+        let file_name = source.file_name()?.to_string_lossy().to_string();
+        parse_name_date(&file_name)
+    };
+    let date = match date_source {
+        DateSource::Mtime => None,
+        DateSource::Ctime => created.map(DateTime::<Local>::from),
+        DateSource::Name => from_name(),
+    };
+    date.or_else(|| modified.map(DateTime::<Local>::from))
+        .unwrap_or_else(Local::now)
+}
+
+// 从文件名中解析 "2024-03-08 21:30:15"、"20240308_213015"、"2024.03.08" 等形式的时间
+fn parse_name_date(file_name: &str) -> Option<DateTime<Local>> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = PATTERN.get_or_init(|| {
+        Regex::new(concat!(
+            r"((?:19|20)\d{2})[-_.]?(0[1-9]|1[0-2])[-_.]?(0[1-9]|[12]\d|3[01])",
+            r"(?:[-_ T.]?([01]\d|2[0-3])[-_:.]?([0-5]\d)(?:[-_:.]?([0-5]\d))?)?",
+        ))
+        .expect("内置的日期正则表达式无效")
+    });
+    let captures = pattern.captures(file_name)?;
+    let number = |index: usize| -> Option<u32> { captures.get(index)?.as_str().parse().ok() };
+    let date = NaiveDate::from_ymd_opt(number(1)? as i32, number(2)?, number(3)?)?;
+    let time = date.and_hms_opt(
+        number(4).unwrap_or(0),
+        number(5).unwrap_or(0),
+        number(6).unwrap_or(0),
+    )?;
+    Local.from_local_datetime(&time).earliest()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(name_pattern: Option<&str>) -> TemplateOptions {
+        TemplateOptions {
+            template: None,
+            date_source: DateSource::Name,
+            name_pattern: name_pattern.map(str::to_string),
+        }
+    }
+
+    // 源文件不需要存在，日期从文件名中解析
+    fn render(template: &str, name_pattern: Option<&str>, source: &str, stem: &str) -> Result<PathBuf, String> {
+        let template = PathTemplate::new(template, &options(name_pattern))?;
+        template.render(&TemplateInput { source: Path::new(source), root: Path::new("/rec"), stem, ext: "mp4" })
+    }
+
+    #[test]
+    fn rejects_invalid_templates() {
+        let cases: [(&str, Option<&str>, &str); 7] = [
+            ("{stem", None, "缺少 }"),
+            ("{name}.{ext}", None, "无法识别的占位符: {name}"),
+            ("{stem:x}", None, "无法识别的占位符"),
+            ("{date}/{stem}", None, "无法识别的占位符: {date}"),
+            ("{date:%Q}/{stem}", None, "日期格式无效"),
+            ("{capture:room}/{stem}", None, "没有对应的分组"),
+            ("{capture:room}/{stem}", Some(r"^(?P<anchor>\w+)"), "没有对应的分组"),
+        ];
+        for (template, name_pattern, message) in cases {
+            let err = PathTemplate::new(template, &options(name_pattern)).err().unwrap_or_default();
+            assert!(err.contains(message), "{}: {}", template, err);
+        }
+        let err = PathTemplate::new("{stem}", &options(Some("("))).err().unwrap_or_default();
+        assert!(err.contains("无效的文件名正则表达式"), "{}", err);
+    }
+
+    #[test]
+    fn renders_placeholders() {
+        let pattern = Some(r"^(?P<room>\d+)-");
+        let cases: [(&str, Option<&str>, &str, &str); 7] = [
+            ("{stem}.{ext}", None, "/rec/a.flv", "a.mp4"),
+            ("{date:%Y/%m}/{stem}.{ext}", None, "/rec/live-2024-03-08-21-30-15.flv", "2024/03/live-2024-03-08-21-30-15.mp4"),
+            ("{date:%Y%m%d_%H%M}.{ext}", None, "/rec/20240308_213015.flv", "20240308_2130.mp4"),
+            ("{parent}/{stem}.{ext}", None, "/rec/room1/a.flv", "room1/a.mp4"),
+            // 工作目录下的文件 {dir} 为空，不产生空的目录层级
+            ("{dir}/{stem}.{ext}", None, "/rec/a.flv", "a.mp4"),
+            ("{dir}/{stem}.{ext}", None, "/rec/2024/03/a.flv", "2024/03/a.mp4"),
+            ("{capture:room}/{capture:1}-{stem}.{ext}", pattern, "/rec/123-a.flv", "123/123-123-a.mp4"),
+        ];
+        for (template, name_pattern, source, expected) in cases {
+            let stem = Path::new(source).file_stem().unwrap().to_str().unwrap();
+            assert_eq!(render(template, name_pattern, source, stem).unwrap(), PathBuf::from(expected), "{}", template);
+        }
+    }
+
+    #[test]
+    fn escapes_separators_and_rejects_paths_outside_output() {
+        // 文件名与分组中的路径分隔符被替换，不产生额外的目录层级
+        let cases: [(&str, Option<&str>, &str, &str); 3] = [
+            ("{stem}.{ext}", None, "a/b\\c", "a_b_c.mp4"),
+            ("{capture:1}/{stem}.{ext}", Some(r"^(.+)$"), "x", "x/x.mp4"),
+            ("{parent}/{stem}.{ext}", None, "a", "rec/a.mp4"),
+        ];
+        for (template, name_pattern, stem, expected) in cases {
+            assert_eq!(render(template, name_pattern, "/rec/x", stem).unwrap(), PathBuf::from(expected), "{}", template);
+        }
+
+        let cases = [
+            ("../{stem}.{ext}", "a"),
+            ("/tmp/{stem}.{ext}", "a"),
+            ("{stem}/", ""),
+            ("{stem}/..", "a"),
+        ];
+        for (template, stem) in cases {
+            let err = render(template, None, "/rec/a.flv", stem).unwrap_err();
+            assert!(err.contains("输出路径无效"), "{}: {}", template, err);
+        }
+
+        // 文件名与 --name-pattern 不匹配时无法生成路径
+        let err = render("{capture:room}/{stem}.{ext}", Some(r"^(?P<room>\d+)-"), "/rec/a.flv", "a").unwrap_err();
+        assert!(err.contains("不匹配"), "{}", err);
+    }
+
+    #[test]
+    fn parses_dates_in_file_names() {
+        let cases: [(&str, Option<&str>); 6] = [
+            ("live-2024-03-08-21-30-15.flv", Some("2024-03-08 21:30:15")),
+            ("20240308_213015.flv", Some("2024-03-08 21:30:15")),
+            ("2024.03.08.flv", Some("2024-03-08 00:00:00")),
+            ("2024-03-08 2130.flv", Some("2024-03-08 21:30:00")),
+            ("2024-13-08.flv", None),
+            ("room-123.flv", None),
+        ];
+        for (file_name, expected) in cases {
+            let parsed = parse_name_date(file_name).map(|date| date.format("%Y-%m-%d %H:%M:%S").to_string());
+            assert_eq!(parsed.as_deref(), expected, "{}", file_name);
+        }
+    }
+}
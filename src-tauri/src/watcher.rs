@@ -0,0 +1,161 @@
+// 监视模式：基于文件系统通知等待目录变化，并通过文件大小判断录制是否结束
+
+use crate::jobs::JobHandle;
+use notify::event::{AccessKind, AccessMode, ModifyKind};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// 等待事件时检查取消标记的间隔
+const RECV_INTERVAL: Duration = Duration::from_millis(100);
+
+// 收到事件后稍作等待，合并短时间内连续到达的事件
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+// 记录每个文件最近一次检查时的大小，大小连续多次不变才认为文件已写完
+pub struct StabilityTracker {
+    required: u32,
+    files: HashMap<PathBuf, (u64, u32)>,
+    // 本轮扫描中因其它原因（如静置时间未到）暂缓处理的文件
+    deferred: HashSet<PathBuf>,
+}
+
+impl StabilityTracker {
+    pub fn new(required: u32) -> Self {
+        StabilityTracker {
+            required,
+            files: HashMap::new(),
+            deferred: HashSet::new(),
+        }
+    }
+
+    // 记录本次检查到的大小，返回文件是否已稳定
+    pub fn check(&mut self, path: &Path, size: u64) -> bool {
+        // 首次检查或大小发生变化时重新计数，之后每次大小不变计一次
+        let count = match self.files.get_mut(path) {
+            Some(entry) if entry.0 == size => {
+                entry.1 += 1;
+                entry.1
+            },
+            _ => {
+                self.files.insert(path.to_path_buf(), (size, 0));
+                0
+            }
+        };
+        count >= self.required
+    }
+
+    // 标记文件需要在下一个检查间隔后复查
+    pub fn defer(&mut self, path: &Path) {
+        self.deferred.insert(path.to_path_buf());
+    }
+
+    // 每轮扫描开始时调用：移除已不存在的文件，避免记录无限增长，并清空上一轮的暂缓标记
+    pub fn prune(&mut self) {
+        self.files.retain(|path, _| path.exists());
+        self.deferred.clear();
+    }
+
+    // 是否还有尚未稳定或暂缓处理的文件，需要在下一个检查间隔后复查
+    pub fn has_pending(&self) -> bool {
+        !self.deferred.is_empty() || self.files.values().any(|(_, count)| *count < self.required)
+    }
+}
+
+// 目录监视器，notify 不可用或指定轮询时按固定间隔触发
+pub struct DirWatcher {
+    extension: String,
+    interval: Duration,
+    // 持有 watcher 以保持监听，drop 后即停止
+    notify: Option<(RecommendedWatcher, Receiver<notify::Result<Event>>)>,
+}
+
+impl DirWatcher {
+    // 返回监视器以及退化为轮询的原因（如果有），recursive 时同时监听所有子目录
+    pub fn new(
+        dir: &Path,
+        extension: &str,
+        recursive: bool,
+        poll: bool,
+        interval: Duration,
+    ) -> (Self, Option<String>) {
+        let mut watcher = DirWatcher {
+            extension: extension.to_string(),
+            interval,
+            notify: None,
+        };
+        if poll {
+            return (watcher, None);
+        }
+
+        let mode = if recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        let (tx, rx) = mpsc::channel();
+        let result = notify::recommended_watcher(tx).and_then(|mut inner| {
+            inner.watch(dir, mode)?;
+            Ok(inner)
+        });
+        match result {
+            Ok(inner) => {
+                watcher.notify = Some((inner, rx));
+                (watcher, None)
+            },
+            Err(e) => (watcher, Some(e.to_string())),
+        }
+    }
+
+    pub fn is_polling(&self) -> bool {
+        self.notify.is_none()
+    }
+
+    // 阻塞直到需要再次扫描目录：收到相关文件的事件，或有待复查的文件且到了检查间隔
+    // 返回 false 表示等待期间任务已被取消
+    pub fn wait(&self, job: &JobHandle, pending: bool) -> bool {
+        let (_, rx) = match &self.notify {
+            Some(notify) => notify,
+            None => return job.sleep(self.interval),
+        };
+
+        let start = Instant::now();
+        loop {
+            if job.is_cancelled() {
+                return false;
+            }
+            match rx.recv_timeout(RECV_INTERVAL) {
+                Ok(Ok(event)) if self.is_relevant(&event) => {
+                    thread::sleep(DEBOUNCE);
+                    rx.try_iter().for_each(drop);
+                    return !job.is_cancelled();
+                },
+                Ok(_) => {},
+                Err(RecvTimeoutError::Timeout) => {},
+                // watcher 已失效，退化为按间隔等待
+                Err(RecvTimeoutError::Disconnected) => return job.sleep(self.interval),
+            }
+            if pending && start.elapsed() >= self.interval {
+                return true;
+            }
+        }
+    }
+
+    // 只关心目标扩展名文件的创建、重命名与写入后关闭
+    // 录制过程中持续的写入事件不触发扫描，由待复查文件的检查间隔负责
+    fn is_relevant(&self, event: &Event) -> bool {
+        let kind_matched = matches!(
+            event.kind,
+            EventKind::Create(_)
+                | EventKind::Modify(ModifyKind::Name(_))
+                | EventKind::Access(AccessKind::Close(AccessMode::Write))
+        );
+        kind_matched
+            && event.paths.iter().any(|path| {
+                path.extension().is_some_and(|ext| ext == self.extension.as_str())
+            })
+    }
+}
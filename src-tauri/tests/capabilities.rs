@@ -0,0 +1,138 @@
+// 解析 ffmpeg 的功能信息，缺少所需功能时处理流程直接报错
+#![cfg(unix)]
+
+use ffmpeg_script::capabilities::{self, CodecKind};
+use ffmpeg_script::events::FileStats;
+use ffmpeg_script::ffmpeg;
+use ffmpeg_script::options;
+use ffmpeg_script::pipeline;
+use ffmpeg_script::report::MemoryReporter;
+use ffmpeg_script::settings;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+const VERSION: &str = "ffmpeg version 6.1.1-3ubuntu5 Copyright (c) 2000-2023 the FFmpeg developers
+built with gcc 13 (Ubuntu 13.2.0-23ubuntu3)
+configuration: --prefix=/usr --enable-gpl --enable-libx264 --enable-libmp3lame
+libavutil      58. 29.100 / 58. 29.100";
+
+const MUXERS: &str = "File formats:
+ D. = Demuxing supported
+ .E = Muxing supported
+ --
+  E flv             FLV (Flash Video)
+  E matroska        Matroska
+  E mp4             MP4 (MPEG-4 Part 14)";
+
+const LIMITED_MUXERS: &str = "File formats:
+ D. = Demuxing supported
+ .E = Muxing supported
+ --
+  E flv             FLV (Flash Video)";
+
+const DEMUXERS: &str = "File formats:
+ D. = Demuxing supported
+ .E = Muxing supported
+ --
+ D  flv             FLV (Flash Video)
+ D  matroska,webm   Matroska / WebM
+ D  mov,mp4,m4a,3gp,3g2,mj2 QuickTime / MOV";
+
+const ENCODERS: &str = "Encoders:
+ V..... = Video
+ A..... = Audio
+ S..... = Subtitle
+ .F.... = Frame-level multithreading
+ ------
+ V....D libx264              libx264 H.264 / AVC / MPEG-4 AVC / MPEG-4 part 10 (codec h264)
+ A....D aac                  AAC (Advanced Audio Coding)
+ S..... mov_text             3GPP Timed Text subtitle";
+
+const DECODERS: &str = "Decoders:
+ V..... = Video
+ A..... = Audio
+ ------
+ VFS..D h264                 H.264 / AVC / MPEG-4 AVC / MPEG-4 part 10
+ A....D aac                  AAC (Advanced Audio Coding)";
+
+const BSFS: &str = "Bitstream filters:
+aac_adtstoasc
+h264_mp4toannexb
+null";
+
+const FILTERS: &str = "Filters:
+  T.. = Timeline support
+  .S. = Slice threading
+  ..C = Command support
+  A = Audio input/output
+  V = Video input/output
+  | = Source or sink filter
+ ..C aresample         A->A       Resample audio data.
+ TSC scale             V->V       Scale the input video size and/or convert the image format.
+ ... nullsink          V->|       Do absolutely nothing with the input video.";
+
+// 按参数输出对应的内容，muxers 为 -muxers 的输出
+fn fake_ffmpeg(dir: &Path, muxers: &str) {
+    fs::create_dir_all(dir).unwrap();
+    let script = format!(
+        "#!/bin/sh\n\
+         for arg in \"$@\"; do\n\
+         case \"$arg\" in\n\
+         -version) cat <<'EOF'\n{}\nEOF\nexit 0 ;;\n\
+         -muxers) cat <<'EOF'\n{}\nEOF\nexit 0 ;;\n\
+         -demuxers) cat <<'EOF'\n{}\nEOF\nexit 0 ;;\n\
+         -encoders) cat <<'EOF'\n{}\nEOF\nexit 0 ;;\n\
+         -decoders) cat <<'EOF'\n{}\nEOF\nexit 0 ;;\n\
+         -bsfs) cat <<'EOF'\n{}\nEOF\nexit 0 ;;\n\
+         -filters) cat <<'EOF'\n{}\nEOF\nexit 0 ;;\n\
+         esac\n\
+         done\n\
+         exit 1\n",
+        VERSION, muxers, DEMUXERS, ENCODERS, DECODERS, BSFS, FILTERS
+    );
+    let path = dir.join("ffmpeg");
+    fs::write(&path, script).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+// 查找结果与功能信息的缓存是进程内共享的状态，放在同一个测试中按顺序执行
+#[test]
+fn capabilities_are_parsed_and_checked_before_processing() {
+    let root = tempfile::tempdir().unwrap();
+    std::env::set_var(settings::CONFIG_DIR_ENV, root.path().join("config"));
+    let full = root.path().join("full");
+    fake_ffmpeg(&full, MUXERS);
+    let limited = root.path().join("limited");
+    fake_ffmpeg(&limited, LIMITED_MUXERS);
+
+    ffmpeg::set_path(Some(full.to_str().unwrap())).unwrap();
+    let caps = capabilities::get().unwrap();
+    assert_eq!(caps.ffmpeg, full.join("ffmpeg"));
+    assert!(caps.version.starts_with("ffmpeg version 6.1.1"));
+    assert!(caps.configuration.iter().any(|option| option == "--enable-libx264"));
+    assert!(caps.muxers.contains("mp4"));
+    assert!(caps.demuxers.contains("mov") && caps.demuxers.contains("mp4") && caps.demuxers.contains("webm"));
+    let libx264 = caps.encoders.iter().find(|codec| codec.name == "libx264").unwrap();
+    assert_eq!(libx264.kind, CodecKind::Video);
+    assert!(libx264.description.starts_with("libx264 H.264"));
+    assert!(caps.encoders.iter().any(|codec| codec.name == "mov_text" && codec.kind == CodecKind::Subtitle));
+    assert!(caps.has_decoder("h264"));
+    assert!(caps.bsfs.contains("aac_adtstoasc") && !caps.bsfs.contains("Bitstream"));
+    assert!(caps.filters.contains("scale") && caps.filters.contains("nullsink"));
+    assert!(!caps.filters.contains("="));
+
+    // 更换 ffmpeg 后重新读取，缺少 mp4 封装时在处理任何文件之前报错
+    ffmpeg::set_path(Some(limited.to_str().unwrap())).unwrap();
+    assert!(!capabilities::get().unwrap().muxers.contains("mp4"));
+
+    let work = root.path().join("work");
+    fs::create_dir_all(&work).unwrap();
+    fs::write(work.join("a.flv"), "fake media").unwrap();
+    let args = ["-c".to_string(), work.display().to_string(), "-s".to_string(), "0".to_string()];
+    let options = options::parse_flv_to_mp4_args(&args).unwrap();
+    let reporter = MemoryReporter::default();
+    let err = pipeline::handle_flv_to_mp4(&options, &reporter, None, &mut FileStats::default(), None).unwrap_err();
+    assert!(err.to_string().contains("mp4 封装格式"), "{}", err);
+    assert!(reporter.events().is_empty());
+}
@@ -0,0 +1,46 @@
+// 命令行工具的退出码：参数值无效与参数校验失败都以 2 退出
+
+use ffmpeg_script::settings;
+use std::process::Command;
+
+fn run_cli(args: &[&str]) -> (Option<i32>, String) {
+    let dir = tempfile::tempdir().unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_ffmpeg-script-cli"))
+        .args(args)
+        .current_dir(dir.path())
+        // 不读取本机保存的设置与预设
+        .env(settings::CONFIG_DIR_ENV, dir.path().join("config"))
+        .output()
+        .unwrap();
+    (output.status.code(), String::from_utf8_lossy(&output.stderr).to_string())
+}
+
+#[test]
+fn invalid_option_values_exit_with_usage_error() {
+    let cases: [&[&str]; 5] = [
+        &["flv2mp4", "--collision", "bogus"],
+        &["flv2mp4", "--remove-mode", "shred"],
+        &["remux", "--format", "avi"],
+        &["avm", "/nonexistent/rec"],
+        &["flv2mp4", "--jobs", "0"],
+    ];
+    for args in cases {
+        let (code, stderr) = run_cli(args);
+        assert_eq!(code, Some(2), "{:?}: {}", args, stderr);
+        assert!(stderr.contains("参数错误"), "{:?}: {}", args, stderr);
+    }
+
+    // clap 无法识别的参数同样以 2 退出
+    assert_eq!(run_cli(&["flv2mp4", "--bogus"]).0, Some(2));
+}
+
+#[test]
+fn invalid_directory_config_exits_with_usage_error() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join(".ffmpeg-script.toml"), "[flv2mp4]\noutput = \"..\"\n").unwrap();
+
+    let (code, stderr) = run_cli(&["flv2mp4", "-c", dir.path().to_str().unwrap()]);
+
+    assert_eq!(code, Some(2), "{}", stderr);
+    assert!(stderr.contains("output"), "{}", stderr);
+}
@@ -0,0 +1,48 @@
+// 返回给界面的错误结构：固定的错误代码、提示文字与附加信息
+
+use ffmpeg_script::error::Error;
+use ffmpeg_script::options::CommandOptions;
+use serde_json::json;
+use std::io;
+
+#[test]
+fn errors_serialize_to_code_message_and_details() {
+    let stderr: String = (1..=30).map(|line| format!("line {}\n", line)).collect();
+    let failed = serde_json::to_value(Error::ffmpeg_failed(Some(1), &stderr)).unwrap();
+    assert_eq!(failed["code"], "FFMPEG_FAILED");
+    assert_eq!(failed["details"]["exitCode"], 1);
+    // 只保留最后 20 行
+    let tail = failed["details"]["stderrTail"].as_str().unwrap();
+    assert!(tail.starts_with("line 11\n") && tail.ends_with("line 30"), "{}", tail);
+    assert!(failed["message"].as_str().unwrap().contains("退出码 1"));
+
+    let io = Error::io("创建输出目录", "/nas/rec/flv-to-mp4", io::Error::from(io::ErrorKind::PermissionDenied));
+    assert_eq!(
+        serde_json::to_value(&io).unwrap()["details"],
+        json!({ "op": "创建输出目录", "path": "/nas/rec/flv-to-mp4" })
+    );
+    assert!(io.to_string().starts_with("创建输出目录失败 /nas/rec/flv-to-mp4"));
+
+    // 文件内容无效与读写失败使用不同的错误代码
+    let invalid = serde_json::to_value(Error::invalid_data("/nas/rec/flv-to-mp4/.ffmpeg-script-ledger.json", "expected value")).unwrap();
+    assert_eq!(invalid["code"], "INVALID_DATA");
+    assert_eq!(invalid["details"]["message"], "expected value");
+
+    let cancelled = serde_json::to_value(Error::Cancelled).unwrap();
+    assert_eq!(cancelled, json!({ "code": "CANCELLED", "message": "任务已取消", "details": null }));
+
+    // 校验错误的全部条目放在 details 中
+    let errors = CommandOptions::parse("avm", &["/nonexistent/rec".to_string(), "-j".to_string(), "0".to_string()]).unwrap_err();
+    let validation = serde_json::to_value(Error::from(errors)).unwrap();
+    assert_eq!(validation["code"], "VALIDATION");
+    let fields: Vec<&str> = validation["details"]["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|error| error["field"].as_str().unwrap())
+        .collect();
+    assert_eq!(fields, ["cwd", "jobs"]);
+
+    let missing = serde_json::to_value(Error::NotFound { kind: "任务", name: "3".to_string() }).unwrap();
+    assert_eq!(missing, json!({ "code": "NOT_FOUND", "message": "任务不存在: 3", "details": { "kind": "任务", "name": "3" } }));
+}
@@ -0,0 +1,59 @@
+// 指定 ffmpeg 路径：校验版本并保存到设置中
+#![cfg(unix)]
+
+use ffmpeg_script::ffmpeg::{self, BinarySource};
+use ffmpeg_script::settings::{self, Settings};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+fn fake_binary(dir: &Path, name: &str, version: &str) {
+    fs::create_dir_all(dir).unwrap();
+    let path = dir.join(name);
+    fs::write(&path, format!("#!/bin/sh\necho \"{} version {} Copyright (c) the FFmpeg developers\"\n", name, version)).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+// 设置与查找结果是进程内共享的状态，放在同一个测试中按顺序执行
+#[test]
+fn set_path_validates_version_and_persists() {
+    let root = tempfile::tempdir().unwrap();
+    std::env::set_var(settings::CONFIG_DIR_ENV, root.path().join("config"));
+
+    let current = root.path().join("current");
+    fake_binary(&current, "ffmpeg", "6.1.1-3ubuntu5");
+    fake_binary(&current, "ffprobe", "6.1.1-3ubuntu5");
+    let old = root.path().join("old");
+    fake_binary(&old, "ffmpeg", "3.4.8");
+
+    // 可以指定可执行文件所在的目录，ffprobe 从同一目录中查找
+    let info = ffmpeg::set_path(Some(current.to_str().unwrap())).unwrap();
+    assert_eq!(info.source, BinarySource::Setting);
+    assert_eq!(info.ffmpeg, current.join("ffmpeg"));
+    assert_eq!(info.ffprobe, Some(current.join("ffprobe")));
+    assert!(info.version.starts_with("ffmpeg version 6.1.1"));
+    assert_eq!(Settings::load().unwrap().ffmpeg_path.as_deref(), current.to_str());
+    assert_eq!(ffmpeg::resolve().unwrap().ffmpeg, current.join("ffmpeg"));
+
+    // 版本过低时不保存
+    let err = ffmpeg::set_path(Some(old.join("ffmpeg").to_str().unwrap())).unwrap_err();
+    assert_eq!(err.code(), "FFMPEG_NOT_FOUND");
+    assert!(err.to_string().contains("版本过低"), "{}", err);
+    assert_eq!(Settings::load().unwrap().ffmpeg_path.as_deref(), current.to_str());
+
+    let err = ffmpeg::set_path(Some(root.path().join("missing").to_str().unwrap())).unwrap_err();
+    assert_eq!(err.code(), "FFMPEG_NOT_FOUND");
+    assert!(err.to_string().contains("不存在"), "{}", err);
+
+    // 找不到的结果被缓存，之后放入的 ffmpeg 在重新查找后才会被使用
+    let later = root.path().join("later");
+    let mut settings = Settings::load().unwrap();
+    settings.ffmpeg_path = later.to_str().map(str::to_string);
+    settings.save().unwrap();
+    ffmpeg::reset();
+    assert!(ffmpeg::resolve().is_err());
+    fake_binary(&later, "ffmpeg", "7.0");
+    assert!(ffmpeg::resolve().is_err());
+    ffmpeg::reset();
+    assert_eq!(ffmpeg::resolve().unwrap().ffmpeg, later.join("ffmpeg"));
+}
@@ -0,0 +1,49 @@
+// 任务注册表：同一目录的重复任务检查与已结束任务的清理
+
+use ffmpeg_script::error::Error;
+use ffmpeg_script::jobs::{JobRegistry, JobState};
+use std::fs;
+
+#[test]
+fn same_directory_written_differently_is_rejected() {
+    let root = tempfile::tempdir().unwrap();
+    let rec = root.path().join("rec");
+    fs::create_dir_all(&rec).unwrap();
+    let registry = JobRegistry::default();
+
+    let job = registry.start("flv2mp4", &rec.display().to_string()).unwrap();
+    for cwd in [format!("{}/", rec.display()), format!("{}/../rec", rec.display())] {
+        let err = registry.start("flv2mp4", &cwd).unwrap_err();
+        assert!(matches!(err, Error::JobRunning { .. }), "{}", err);
+    }
+    // 其它类型的任务不受影响，记录的是规范化后的目录
+    registry.start("avm", &format!("{}/", rec.display())).unwrap();
+    let info = registry.status(job.id).unwrap();
+    assert_eq!(info.cwd, fs::canonicalize(&rec).unwrap().display().to_string());
+
+    // 任务结束后可以再次执行
+    registry.finish(job.id, &Ok(()));
+    registry.start("flv2mp4", &format!("{}/", rec.display())).unwrap();
+}
+
+#[test]
+fn only_recent_finished_jobs_are_kept() {
+    let root = tempfile::tempdir().unwrap();
+    let registry = JobRegistry::default();
+    let running = registry.start("remux", &root.path().display().to_string()).unwrap();
+
+    let mut ids = Vec::new();
+    for i in 0..60 {
+        let dir = root.path().join(i.to_string());
+        fs::create_dir_all(&dir).unwrap();
+        let job = registry.start("flv2mp4", &dir.display().to_string()).unwrap();
+        registry.finish(job.id, &Err(Error::Cancelled));
+        ids.push(job.id);
+    }
+
+    let jobs = registry.list();
+    assert_eq!(jobs.len(), 51);
+    assert_eq!(registry.status(running.id).unwrap().state, JobState::Running);
+    assert!(registry.status(ids[9]).is_err());
+    assert_eq!(registry.status(ids[10]).unwrap().state, JobState::Failed);
+}
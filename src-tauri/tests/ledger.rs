@@ -0,0 +1,36 @@
+// 处理记录：同一输出目录被多个 Ledger 同时使用时，以磁盘上的最新记录为准
+
+use ffmpeg_script::ledger::Ledger;
+use std::fs;
+
+#[test]
+fn forgotten_entries_are_not_written_back_by_an_open_ledger() {
+    let dir = tempfile::tempdir().unwrap();
+    let (a, b, c) = (dir.path().join("a.flv"), dir.path().join("b.flv"), dir.path().join("c.flv"));
+    for file in [&a, &b, &c] {
+        fs::write(file, "flv").unwrap();
+    }
+    let dest = dir.path().join("out.mp4");
+
+    // 执行中的任务打开的记录
+    let job = Ledger::open(dir.path()).unwrap();
+    job.record(&a, &dest, "flv2mp4").unwrap();
+    job.record_failure(&b, "Invalid data").unwrap();
+
+    // 界面在另一个 Ledger 中删除了记录
+    let removed = Ledger::open(dir.path()).unwrap().forget(&[a.display().to_string(), b.display().to_string()]).unwrap();
+    assert_eq!(removed, 2);
+
+    // 任务之后的写入不能把删除的记录恢复
+    job.record(&c, &dest, "flv2mp4").unwrap();
+    let reopened = Ledger::open(dir.path()).unwrap();
+    assert!(!reopened.contains(&a) && reopened.contains(&c));
+    assert!(reopened.failures().is_empty());
+    assert!(!job.contains(&a));
+
+    // 其它任务写入的失败次数不会被过期的内存记录覆盖
+    let other = Ledger::open(dir.path()).unwrap();
+    assert_eq!(other.record_failure(&b, "Invalid data").unwrap(), 1);
+    assert_eq!(job.record_failure(&b, "Invalid data").unwrap(), 2);
+    assert_eq!(other.record_failure(&b, "Invalid data").unwrap(), 3);
+}
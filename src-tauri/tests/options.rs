@@ -0,0 +1,74 @@
+// 命令参数的解析与校验：格式错误与不合理的组合在开始处理前一次全部报告
+
+use ffmpeg_script::options::{self, CommandOptions, CommandParams};
+use serde_json::json;
+
+fn args(values: &[&str]) -> Vec<String> {
+    values.iter().map(|value| value.to_string()).collect()
+}
+
+fn fields(errors: &[options::ValidationError]) -> Vec<&str> {
+    errors.iter().map(|error| error.field.as_str()).collect()
+}
+
+#[test]
+fn malformed_values_and_unknown_flags_are_rejected() {
+    let err = options::parse_flv_to_mp4_args(&args(&["-c", ".", "-t", "abc"])).unwrap_err();
+    assert!(err.contains("--timeout 的值无效: abc"), "{}", err);
+    let err = options::parse_remux_args(&args(&["-c", ".", "--jobs", "-1"])).unwrap_err();
+    assert!(err.contains("的值无效"), "{}", err);
+    let err = options::parse_flv_to_mp4_args(&args(&["-c", ".", "--no-such-flag"])).unwrap_err();
+    assert!(err.contains("无法识别的参数: --no-such-flag"), "{}", err);
+}
+
+#[test]
+fn all_validation_errors_are_reported_together() {
+    let root = tempfile::tempdir().unwrap();
+    let missing = root.path().join("missing");
+    let errors = CommandOptions::parse("flv2mp4", &args(&["-c", missing.to_str().unwrap(), "-w", "-t", "0", "-j", "0"])).unwrap_err();
+    assert_eq!(fields(&errors), ["cwd", "timeout", "jobs"]);
+    assert!(errors[0].message.contains("工作目录不存在"));
+
+    // 输出到工作目录本身或其上级目录会再次扫描到转换结果
+    let cwd = root.path().join("rec");
+    std::fs::create_dir_all(&cwd).unwrap();
+    for output in [cwd.clone(), root.path().to_path_buf()] {
+        let errors = CommandOptions::parse("remux", &args(&["-c", cwd.to_str().unwrap(), "-o", output.to_str().unwrap()])).unwrap_err();
+        assert_eq!(fields(&errors), ["output"]);
+    }
+    // 输出目录位于工作目录内是允许的
+    let output = cwd.join("converted");
+    assert!(CommandOptions::parse("remux", &args(&["-c", cwd.to_str().unwrap(), "-o", output.to_str().unwrap()])).is_ok());
+
+    let errors = CommandOptions::parse("unknown", &[]).unwrap_err();
+    assert_eq!(fields(&errors), ["commandType"]);
+}
+
+#[test]
+fn typed_params_use_command_line_defaults_and_reject_unknown_fields() {
+    let root = tempfile::tempdir().unwrap();
+    let cwd = root.path().display().to_string();
+
+    let params: CommandParams = serde_json::from_value(json!({ "commandType": "flv2mp4", "cwd": cwd })).unwrap();
+    let CommandOptions::FlvToMp4(parsed) = params.into_options().unwrap() else {
+        panic!("命令类型错误");
+    };
+    let from_args = options::parse_flv_to_mp4_args(&args(&["-c", &cwd])).unwrap();
+    assert_eq!(parsed.output_dir, from_args.output_dir);
+    assert_eq!(parsed.timeout, from_args.timeout);
+    assert_eq!(parsed.jobs, 1);
+
+    let params: CommandParams = serde_json::from_value(json!({
+        "commandType": "remux",
+        "cwd": cwd,
+        "extensions": ["ts", "mkv"],
+        "format": "avi",
+        "collision": "replace",
+        "jobs": 0,
+        "bogus": true
+    }))
+    .unwrap();
+    let errors = params.into_options().unwrap_err();
+    assert_eq!(fields(&errors), ["bogus", "format", "collision", "jobs"]);
+    assert_eq!(errors[0].message, "无法识别的参数");
+}
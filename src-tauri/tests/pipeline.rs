@@ -0,0 +1,399 @@
+// 用假的 ffmpeg / ffprobe 脚本在临时目录中执行 flv2mp4 与 avm 的处理流程
+// 假的 ffmpeg 把第一个输入文件复制到输出文件，输入文件名包含 broken 时以非 0 退出
+// 假的 ffprobe 在文件名包含 corrupt 时以非 0 退出
+#![cfg(unix)]
+
+use ffmpeg_script::error::Error;
+use ffmpeg_script::events::{FileStats, JobEvent, PlanAction, SkipReason};
+use ffmpeg_script::jobs::JobRegistry;
+use ffmpeg_script::options;
+use ffmpeg_script::pipeline;
+use ffmpeg_script::report::MemoryReporter;
+use ffmpeg_script::settings;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::Once;
+use std::thread;
+use std::time::Duration;
+use tempfile::TempDir;
+
+const FAKE_FFMPEG: &str = r#"#!/bin/sh
+if [ "$1" = "-version" ]; then
+  echo "ffmpeg version 6.1-fake"
+  exit 0
+fi
+# 不提供功能信息，处理前的功能检查会被跳过
+case "$2" in
+  -muxers|-demuxers|-encoders|-decoders|-bsfs|-filters) exit 1 ;;
+esac
+input=""
+output=""
+while [ $# -gt 0 ]; do
+  if [ "$1" = "-i" ] && [ -z "$input" ]; then
+    input="$2"
+  fi
+  output="$1"
+  shift
+done
+case "$input" in
+  *broken*)
+    echo "$input: Invalid data found when processing input" >&2
+    exit 1
+    ;;
+esac
+echo "  Duration: 00:00:10.00, start: 0.000000, bitrate: 1 kb/s" >&2
+cp "$input" "$output"
+echo "out_time_us=10000000"
+echo "progress=end"
+"#;
+
+const FAKE_FFPROBE: &str = r#"#!/bin/sh
+if [ "$1" = "-version" ]; then
+  echo "ffprobe version 6.1-fake"
+  exit 0
+fi
+for last; do :; done
+case "$last" in
+  *corrupt*)
+    echo "$last: Invalid data found when processing input" >&2
+    exit 1
+    ;;
+esac
+cat <<'EOF'
+{
+  "streams": [
+    { "index": 0, "codec_type": "video", "codec_name": "h264", "width": 1920, "height": 1080 },
+    { "index": 1, "codec_type": "audio", "codec_name": "aac" }
+  ],
+  "format": { "format_name": "flv", "duration": "10.000000" }
+}
+EOF
+"#;
+
+// 把假的 ffmpeg / ffprobe 放到 PATH 的最前面，所有测试共用
+fn install_fake_ffmpeg() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let bin_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("fake-ffmpeg");
+        fs::create_dir_all(&bin_dir).unwrap();
+        for (name, script) in [("ffmpeg", FAKE_FFMPEG), ("ffprobe", FAKE_FFPROBE)] {
+            let path = bin_dir.join(name);
+            fs::write(&path, script).unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        // 不读取本机设置中指定的 ffmpeg
+        std::env::set_var(settings::CONFIG_DIR_ENV, Path::new(env!("CARGO_TARGET_TMPDIR")).join("config"));
+        let path = std::env::var_os("PATH").unwrap_or_default();
+        let mut paths = vec![bin_dir];
+        paths.extend(std::env::split_paths(&path));
+        std::env::set_var("PATH", std::env::join_paths(paths).unwrap());
+    });
+}
+
+fn workspace(files: &[&str]) -> TempDir {
+    install_fake_ffmpeg();
+    let dir = tempfile::tempdir().unwrap();
+    for file in files {
+        let path = dir.path().join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, format!("fake media: {}", file)).unwrap();
+    }
+    dir
+}
+
+fn args(dir: &Path, extra: &[&str]) -> Vec<String> {
+    // 测试文件刚刚写入，-s 0 不等待录制结束
+    let mut args = vec!["-c".to_string(), dir.display().to_string(), "-s".to_string(), "0".to_string()];
+    args.extend(extra.iter().map(|arg| arg.to_string()));
+    args
+}
+
+fn skipped(reporter: &MemoryReporter) -> Vec<(String, SkipReason)> {
+    reporter
+        .events()
+        .into_iter()
+        .filter_map(|event| match event {
+            JobEvent::Skipped { file, reason } => Some((file, reason)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn file_name(path: &str) -> String {
+    PathBuf::from(path).file_name().unwrap().to_string_lossy().to_string()
+}
+
+#[test]
+fn flv_to_mp4_converts_every_flv_file() {
+    let dir = workspace(&["a.flv", "b.flv", "notes.txt"]);
+    let options = options::parse_flv_to_mp4_args(&args(dir.path(), &[])).unwrap();
+    let reporter = MemoryReporter::default();
+    let mut stats = FileStats::default();
+
+    pipeline::handle_flv_to_mp4(&options, &reporter, None, &mut stats, None).unwrap();
+
+    assert_eq!((stats.converted, stats.skipped, stats.failed), (2, 0, 0));
+    let output = dir.path().join("flv-to-mp4");
+    assert_eq!(fs::read_to_string(output.join("a.mp4")).unwrap(), "fake media: a.flv");
+    assert_eq!(fs::read_to_string(output.join("b.mp4")).unwrap(), "fake media: b.flv");
+    assert!(!output.join("notes.mp4").exists());
+    // 没有 -r 时保留源文件
+    assert!(dir.path().join("a.flv").exists());
+    let succeeded = reporter.events().iter().filter(|event| matches!(event, JobEvent::Succeeded { .. })).count();
+    assert_eq!(succeeded, 2);
+}
+
+#[test]
+fn flv_to_mp4_skips_files_already_converted() {
+    let dir = workspace(&["a.flv"]);
+    let options = options::parse_flv_to_mp4_args(&args(dir.path(), &[])).unwrap();
+    pipeline::handle_flv_to_mp4(&options, &MemoryReporter::default(), None, &mut FileStats::default(), None).unwrap();
+
+    let reporter = MemoryReporter::default();
+    let mut stats = FileStats::default();
+    pipeline::handle_flv_to_mp4(&options, &reporter, None, &mut stats, None).unwrap();
+
+    assert_eq!((stats.converted, stats.skipped), (0, 1));
+    let skipped = skipped(&reporter);
+    assert_eq!(skipped.len(), 1);
+    assert_eq!(file_name(&skipped[0].0), "a.flv");
+    assert!(matches!(skipped[0].1, SkipReason::AlreadyProcessed));
+}
+
+#[test]
+fn flv_to_mp4_no_skip_converts_again() {
+    let dir = workspace(&["a.flv"]);
+    let options = options::parse_flv_to_mp4_args(&args(dir.path(), &[])).unwrap();
+    pipeline::handle_flv_to_mp4(&options, &MemoryReporter::default(), None, &mut FileStats::default(), None).unwrap();
+    fs::write(dir.path().join("a.flv"), "fake media: updated").unwrap();
+
+    let options = options::parse_flv_to_mp4_args(&args(dir.path(), &["--no-skip"])).unwrap();
+    let mut stats = FileStats::default();
+    pipeline::handle_flv_to_mp4(&options, &MemoryReporter::default(), None, &mut stats, None).unwrap();
+
+    assert_eq!((stats.converted, stats.skipped), (1, 0));
+    let converted = fs::read_to_string(dir.path().join("flv-to-mp4/a.mp4")).unwrap();
+    assert_eq!(converted, "fake media: updated");
+}
+
+#[test]
+fn flv_to_mp4_reports_failures_and_keeps_going() {
+    let dir = workspace(&["broken.flv", "good.flv"]);
+    let options = options::parse_flv_to_mp4_args(&args(dir.path(), &[])).unwrap();
+    let reporter = MemoryReporter::default();
+    let mut stats = FileStats::default();
+
+    pipeline::handle_flv_to_mp4(&options, &reporter, None, &mut stats, None).unwrap();
+
+    assert_eq!((stats.converted, stats.failed), (1, 1));
+    let output = dir.path().join("flv-to-mp4");
+    assert!(output.join("good.mp4").exists());
+    assert!(!output.join("broken.mp4").exists());
+    let failed: Vec<String> = reporter
+        .events()
+        .into_iter()
+        .filter_map(|event| match event {
+            JobEvent::Failed { file, stderr } => {
+                assert!(stderr.contains("Invalid data"));
+                Some(file_name(&file))
+            },
+            _ => None,
+        })
+        .collect();
+    assert_eq!(failed, ["broken.flv"]);
+}
+
+// ffprobe 无法读取的文件按处理失败记录，达到最大尝试次数后移到隔离目录
+#[test]
+fn unreadable_files_are_retried_then_quarantined() {
+    let dir = workspace(&["corrupt.flv", "good.flv"]);
+    let retry = ["--max-attempts", "2", "--retry-delay", "0", "--quarantine"];
+    let options = options::parse_flv_to_mp4_args(&args(dir.path(), &retry)).unwrap();
+
+    // 预演时只标记为无法读取，不计入失败次数
+    let dry_run = options::parse_flv_to_mp4_args(&args(dir.path(), &["--dry-run"])).unwrap();
+    let plan = pipeline::handle_flv_to_mp4(&dry_run, &MemoryReporter::default(), None, &mut FileStats::default(), None).unwrap();
+    let unreadable: Vec<String> = plan
+        .iter()
+        .filter(|entry| matches!(entry.reason, Some(SkipReason::UnreadableMedia)))
+        .map(|entry| file_name(&entry.file))
+        .collect();
+    assert_eq!(unreadable, ["corrupt.flv"]);
+
+    for attempt in 1..=2 {
+        let reporter = MemoryReporter::default();
+        let mut stats = FileStats::default();
+        pipeline::handle_flv_to_mp4(&options, &reporter, None, &mut stats, None).unwrap();
+
+        assert_eq!(stats.failed, 1, "第 {} 次", attempt);
+        assert!(skipped(&reporter).iter().all(|(_, reason)| !matches!(reason, SkipReason::MissingStream)));
+        let failed = reporter.events().into_iter().any(|event| {
+            matches!(event, JobEvent::Failed { file, stderr } if file_name(&file) == "corrupt.flv" && stderr.contains("Invalid data"))
+        });
+        assert!(failed, "第 {} 次", attempt);
+    }
+
+    assert!(!dir.path().join("corrupt.flv").exists());
+    assert!(dir.path().join("flv-to-mp4/failed/corrupt.flv").exists());
+    assert!(dir.path().join("flv-to-mp4/good.mp4").exists());
+}
+
+// 监视模式每次执行都会再次扫描到已转换的文件，跳过的数量不能随执行次数累加
+#[test]
+fn watch_mode_does_not_accumulate_skipped_files() {
+    let dir = workspace(&["a.flv"]);
+    let options = options::parse_flv_to_mp4_args(&args(dir.path(), &["-w", "-p", "-t", "1", "-n", "0"])).unwrap();
+    let registry = JobRegistry::default();
+    let job = registry.start("flv2mp4", &options.cwd).unwrap();
+    let handle = job.clone();
+    let canceller = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(3500));
+        handle.cancel();
+    });
+    let reporter = MemoryReporter::default();
+    let mut stats = FileStats::default();
+
+    pipeline::watch_flv_to_mp4(&options, &reporter, &job, &mut stats);
+    canceller.join().unwrap();
+
+    let ticks = reporter.events().iter().filter(|event| matches!(event, JobEvent::WatchTick { .. })).count();
+    assert!(ticks >= 3, "{}", ticks);
+    assert_eq!((stats.converted, stats.skipped, stats.failed), (1, 1, 0));
+}
+
+#[test]
+fn flv_to_mp4_recursive_keeps_subdirectories() {
+    let dir = workspace(&["a.flv", "room/b.flv"]);
+    let options = options::parse_flv_to_mp4_args(&args(dir.path(), &["-R"])).unwrap();
+    let mut stats = FileStats::default();
+
+    pipeline::handle_flv_to_mp4(&options, &MemoryReporter::default(), None, &mut stats, None).unwrap();
+
+    assert_eq!(stats.converted, 2);
+    let output = dir.path().join("flv-to-mp4");
+    assert!(output.join("a.mp4").exists());
+    assert!(output.join("room/b.mp4").exists());
+}
+
+#[test]
+fn flv_to_mp4_dry_run_only_returns_plan() {
+    let dir = workspace(&["a.flv", "b.flv"]);
+    let options = options::parse_flv_to_mp4_args(&args(dir.path(), &["--dry-run"])).unwrap();
+    let reporter = MemoryReporter::default();
+
+    let plan = pipeline::handle_flv_to_mp4(&options, &reporter, None, &mut FileStats::default(), None).unwrap();
+
+    assert_eq!(plan.len(), 2);
+    assert!(plan.iter().all(|entry| matches!(entry.action, PlanAction::Process)));
+    assert!(plan[0].argv[0].ends_with("fake-ffmpeg/ffmpeg"));
+    assert!(plan[0].dest.as_deref().unwrap().ends_with("a.mp4"));
+    assert!(!dir.path().join("flv-to-mp4").exists());
+    let planned = reporter.events().iter().filter(|event| matches!(event, JobEvent::Planned { .. })).count();
+    assert_eq!(planned, 2);
+}
+
+#[test]
+fn avm_merges_matching_pairs() {
+    let dir = workspace(&["demo1_audio.mp4", "demo1_video.mp4", "demo2_audio.webm", "demo2_video.webm", "lonely_audio.mp3"]);
+    let options = options::parse_avm_args(&[dir.path().display().to_string()]).unwrap();
+    let reporter = MemoryReporter::default();
+    let mut stats = FileStats::default();
+
+    pipeline::handle_audio_video_merger(&options, &reporter, None, &mut stats).unwrap();
+
+    assert_eq!((stats.converted, stats.skipped, stats.failed), (2, 1, 0));
+    let output = dir.path().join("audio-video-merger");
+    assert!(output.join("demo1.mp4").exists());
+    assert!(output.join("demo2.webm").exists());
+    let skipped = skipped(&reporter);
+    assert_eq!(file_name(&skipped[0].0), "lonely_audio.mp3");
+    assert!(matches!(skipped[0].1, SkipReason::NoMatchingVideo));
+}
+
+#[test]
+fn avm_skips_pairs_already_merged() {
+    let dir = workspace(&["demo_audio.mp4", "demo_video.mp4"]);
+    let options = options::parse_avm_args(&[dir.path().display().to_string()]).unwrap();
+    pipeline::handle_audio_video_merger(&options, &MemoryReporter::default(), None, &mut FileStats::default()).unwrap();
+
+    let reporter = MemoryReporter::default();
+    let mut stats = FileStats::default();
+    pipeline::handle_audio_video_merger(&options, &reporter, None, &mut stats).unwrap();
+
+    assert_eq!((stats.converted, stats.skipped), (0, 1));
+    assert!(matches!(skipped(&reporter)[0].1, SkipReason::AlreadyProcessed));
+}
+
+#[test]
+fn avm_reports_empty_directory() {
+    let dir = workspace(&["readme.txt"]);
+    let options = options::parse_avm_args(&[dir.path().display().to_string()]).unwrap();
+    let reporter = MemoryReporter::default();
+
+    pipeline::handle_audio_video_merger(&options, &reporter, None, &mut FileStats::default()).unwrap();
+
+    assert!(reporter.messages().iter().any(|msg| msg.contains("未发现可合并的音视频文件")));
+    assert!(reporter.events().is_empty());
+}
+
+#[test]
+fn flv_to_mp4_applies_directory_config() {
+    let dir = workspace(&["a.flv"]);
+    fs::write(
+        dir.path().join(".ffmpeg-script.toml"),
+        "[flv2mp4]\noutput = \"converted\"\nffmpeg-args = [\"-movflags\", \"+faststart\"]\n",
+    ).unwrap();
+    let options = options::parse_flv_to_mp4_args(&args(dir.path(), &["--dry-run"])).unwrap();
+
+    let plan = pipeline::handle_flv_to_mp4(&options, &MemoryReporter::default(), None, &mut FileStats::default(), None).unwrap();
+
+    assert!(plan[0].dest.as_deref().unwrap().ends_with("converted/a.mp4"));
+    let argv = &plan[0].argv;
+    assert_eq!(argv[argv.len() - 3..argv.len() - 1], ["-movflags", "+faststart"]);
+}
+
+#[test]
+fn avm_pairs_files_with_configured_suffixes() {
+    let dir = workspace(&["demo_a.mp4", "demo_v.mp4", "other_audio.mp4", "other_video.mp4"]);
+    fs::write(dir.path().join(".ffmpeg-script.toml"), "[avm]\naudio-suffix = \"_a\"\nvideo-suffix = \"_v\"\n").unwrap();
+    let options = options::parse_avm_args(&[dir.path().display().to_string()]).unwrap();
+    let mut stats = FileStats::default();
+
+    pipeline::handle_audio_video_merger(&options, &MemoryReporter::default(), None, &mut stats).unwrap();
+
+    assert_eq!(stats.converted, 1);
+    let output = dir.path().join("audio-video-merger");
+    assert_eq!(fs::read_to_string(output.join("demo.mp4")).unwrap(), "fake media: demo_v.mp4");
+    assert!(!output.join("other.mp4").exists());
+}
+
+#[test]
+fn directory_config_with_unknown_key_is_rejected() {
+    let dir = workspace(&["a.flv"]);
+    fs::write(dir.path().join(".ffmpeg-script.toml"), "[flv2mp4]\noutpt = \"converted\"\n").unwrap();
+    let options = options::parse_flv_to_mp4_args(&args(dir.path(), &[])).unwrap();
+
+    let err = pipeline::handle_flv_to_mp4(&options, &MemoryReporter::default(), None, &mut FileStats::default(), None).unwrap_err();
+
+    assert!(err.to_string().contains(".ffmpeg-script.toml"), "{}", err);
+    assert!(!dir.path().join("flv-to-mp4").exists());
+}
+
+// 目录配置合并后的参数同样需要通过校验，输出到工作目录本身会把结果再次当作源文件
+#[test]
+fn directory_config_is_validated_after_merging() {
+    let dir = workspace(&["a.flv"]);
+    fs::write(dir.path().join(".ffmpeg-script.toml"), "[flv2mp4]\noutput = \".\"\nduration-tolerance = -1.0\n").unwrap();
+    let options = options::parse_flv_to_mp4_args(&args(dir.path(), &[])).unwrap();
+    options.validate().unwrap();
+
+    let err = pipeline::handle_flv_to_mp4(&options, &MemoryReporter::default(), None, &mut FileStats::default(), None).unwrap_err();
+
+    let Error::Validation(errors) = &err else {
+        panic!("{}", err);
+    };
+    let fields: Vec<&str> = errors.iter().map(|error| error.field.as_str()).collect();
+    assert_eq!(fields, ["output", "durationTolerance"]);
+    assert!(!dir.path().join("a.mp4").exists());
+}
@@ -0,0 +1,91 @@
+// 预设的保存、替换与删除，以及旧版本设置文件的升级
+
+use ffmpeg_script::options::CommandOptions;
+use ffmpeg_script::presets::{self, Preset};
+use ffmpeg_script::settings::{self, Settings};
+use serde_json::{json, Value};
+use std::fs;
+
+fn preset(name: &str, params: Value) -> Preset {
+    Preset {
+        name: name.to_string(),
+        params: serde_json::from_value(params).unwrap(),
+        form: None,
+    }
+}
+
+// 设置文件是进程内共享的状态，放在同一个测试中按顺序执行
+#[test]
+fn presets_are_saved_in_settings_and_old_settings_are_migrated() {
+    let root = tempfile::tempdir().unwrap();
+    let config = root.path().join("config");
+    let rec = root.path().join("rec");
+    fs::create_dir_all(&rec).unwrap();
+    let rec = rec.display().to_string();
+    std::env::set_var(settings::CONFIG_DIR_ENV, &config);
+
+    // 版本 1 的设置文件没有预设，读取时升级，原有的设置保留
+    fs::create_dir_all(&config).unwrap();
+    fs::write(config.join("settings.json"), r#"{ "version": 1, "ffmpegPath": "/opt/ffmpeg/bin" }"#).unwrap();
+    let settings = Settings::load().unwrap();
+    assert_eq!(settings.version, 2);
+    assert_eq!(settings.ffmpeg_path.as_deref(), Some("/opt/ffmpeg/bin"));
+    assert!(presets::list().unwrap().is_empty());
+
+    presets::save(preset(" NAS 归档 ", json!({ "commandType": "flv2mp4", "cwd": rec, "archive": true, "remove": true }))).unwrap();
+    let saved = presets::save(preset("本地快速转换", json!({ "commandType": "remux", "cwd": rec, "format": "mkv" }))).unwrap();
+    assert_eq!(saved.len(), 2);
+    let Ok(CommandOptions::FlvToMp4(options)) = presets::find("NAS 归档").unwrap().params.into_options() else {
+        panic!("NAS 归档");
+    };
+    assert!(options.archive && options.remove);
+
+    // 同名的预设被替换
+    presets::save(preset("NAS 归档", json!({ "commandType": "flv2mp4", "cwd": rec, "watch": true }))).unwrap();
+    let listed = presets::list().unwrap();
+    assert_eq!(listed.len(), 2);
+    let Ok(CommandOptions::FlvToMp4(options)) = listed[0].params.clone().into_options() else {
+        panic!("NAS 归档");
+    };
+    assert!(options.watch && !options.archive);
+
+    // 保存前按执行时的规则检查参数，升级后的设置与预设一起写回
+    assert!(presets::save(preset("坏预设", json!({ "commandType": "remux", "cwd": rec, "format": "avi" }))).is_err());
+    assert!(presets::save(preset("坏预设", json!({ "commandType": "avm", "cwd": "/nonexistent/rec" }))).is_err());
+    assert!(presets::save(preset("  ", json!({ "commandType": "avm", "cwd": rec }))).is_err());
+    let content = fs::read_to_string(config.join("settings.json")).unwrap();
+    assert!(content.contains("\"version\": 2"), "{}", content);
+    assert!(content.contains("/opt/ffmpeg/bin"), "{}", content);
+
+    let remaining = presets::delete("本地快速转换").unwrap();
+    assert_eq!(remaining.len(), 1);
+    assert!(presets::delete("本地快速转换").unwrap_err().to_string().contains("预设不存在"));
+    assert!(presets::find("本地快速转换").is_err());
+
+    // 无法读取的预设不影响其它预设，也不会在保存时丢失
+    fs::write(config.join("settings.json"), json!({
+        "version": 2,
+        "presets": [
+            { "name": "NAS 归档", "params": { "commandType": "flv2mp4", "cwd": rec, "archive": true } },
+            { "name": "新命令", "params": { "commandType": "transcode", "cwd": rec } },
+            { "name": "坏参数", "params": { "commandType": "remux", "jobs": "many" } }
+        ]
+    }).to_string()).unwrap();
+    let settings = Settings::load().unwrap();
+    let names: Vec<&str> = settings.presets.iter().map(|preset| preset.name.as_str()).collect();
+    assert_eq!(names, ["NAS 归档"]);
+    assert_eq!(settings.invalid_presets.len(), 2);
+    assert_eq!(settings.invalid_presets[0]["name"], "新命令");
+    presets::save(preset("合并", json!({ "commandType": "avm", "cwd": rec }))).unwrap();
+    let saved: Value = serde_json::from_str(&fs::read_to_string(config.join("settings.json")).unwrap()).unwrap();
+    assert_eq!(saved["presets"].as_array().unwrap().len(), 2);
+    assert_eq!(saved["invalidPresets"][1]["params"]["jobs"], "many");
+    // 再次读取时仍然保留，不会重复
+    assert_eq!(Settings::load().unwrap().invalid_presets.len(), 2);
+
+    // 更新版本的应用写入的设置不能被降级读取
+    fs::write(config.join("settings.json"), r#"{ "version": 99 }"#).unwrap();
+    let err = Settings::load().unwrap_err();
+    assert_eq!(err.code(), "INVALID_DATA");
+    assert!(err.to_string().contains("请升级应用"), "{}", err);
+}